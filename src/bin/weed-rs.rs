@@ -0,0 +1,516 @@
+//! Command line tool for day-to-day operations on a SeaweedFS cluster
+//!
+//! Built with the `cli` feature, every command goes through the same
+//! [Master](rusty_weed::master::Master) and [Volume](rusty_weed::volume::Volume) as the library.
+
+use std::{
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::UNIX_EPOCH,
+};
+
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand};
+use rusty_weed::{
+    master::{
+        AssignKeyOptions, GrowVolumeOptions, LookupVolumeOptions, Master, VacuumVolumeOptions,
+    },
+    utils::{Location, ReplicationType, FID, TTL},
+    volume::{FileMeta, UploadFileOptions},
+};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWriteExt};
+
+type CliResult = Result<Output, Box<dyn Error>>;
+
+#[derive(Parser, Debug)]
+#[command(name = "weed-rs", version, about = "Operate a SeaweedFS cluster")]
+struct Cli {
+    /// Address of the master like localhost:9333
+    #[arg(short, long, env = "WEED_MASTER", default_value = "localhost:9333", global = true)]
+    master: String,
+
+    /// Print the result as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Assign a file id
+    Assign {
+        /// Number of file ids to reserve
+        #[arg(short, long)]
+        count: Option<u32>,
+        #[command(flatten)]
+        placement: Placement,
+    },
+    /// Show the locations of a volume id or file id
+    Lookup { id: String },
+    /// Upload a file and print its file id
+    Put {
+        file: PathBuf,
+        #[command(flatten)]
+        placement: Placement,
+    },
+    /// Download a file, to stdout unless an output file is given
+    Get {
+        fid: FID,
+        /// Needed with --json, which would be mixed into the content on stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Delete a file
+    Rm { fid: FID },
+    /// Show the size, type and pairs of a file
+    Stat { fid: FID },
+    /// Create new volumes
+    Grow {
+        /// Number of volumes to create
+        #[arg(short, long)]
+        count: Option<u32>,
+        #[command(flatten)]
+        placement: Placement,
+    },
+    /// Compact volumes with deleted content
+    Vacuum {
+        /// Share of deleted content above which a volume is compacted
+        #[arg(short, long)]
+        garbage_threshold: Option<f32>,
+        #[arg(long)]
+        collection: Option<String>,
+        #[arg(long)]
+        volume_id: Option<u32>,
+    },
+    /// Show data centers, racks, volume servers and their volumes
+    Topology,
+}
+
+/// Where new files or volumes are placed
+#[derive(Args, Debug, Default)]
+struct Placement {
+    #[arg(long)]
+    collection: Option<String>,
+    /// Replication like 010
+    #[arg(short, long)]
+    replication: Option<ReplicationType>,
+    /// Time to live like 3d
+    #[arg(short, long)]
+    ttl: Option<TTL>,
+    #[arg(long)]
+    data_center: Option<String>,
+}
+
+/// Result of a command as JSON and as text for humans
+struct Output {
+    json: Value,
+    text: String,
+}
+
+impl Output {
+    fn new(json: Value, text: impl Into<String>) -> Output {
+        Output {
+            json,
+            text: text.into(),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = parse();
+
+    match run(&cli).await {
+        Ok(output) => {
+            match cli.json {
+                true => println!("{}", output.json),
+                false if output.text.is_empty() => (),
+                false => println!("{}", output.text),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            match cli.json {
+                true => eprintln!("{}", json!({ "error": err.to_string() })),
+                false => eprintln!("error: {}", err),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parses the arguments, rejecting combinations clap cannot express because --json is global
+fn parse() -> Cli {
+    let cli = Cli::parse();
+
+    if let (true, Command::Get { output: None, .. }) = (cli.json, &cli.command) {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--json needs --output, the content would be mixed into the JSON on stdout",
+            )
+            .exit();
+    }
+    cli
+}
+
+async fn run(cli: &Cli) -> CliResult {
+    let master: Master = cli.master.parse()?;
+
+    match &cli.command {
+        Command::Assign { count, placement } => assign(&master, *count, placement).await,
+        Command::Lookup { id } => lookup(&master, id).await,
+        Command::Put { file, placement } => put(&master, file, placement).await,
+        Command::Get { fid, output } => get(&master, fid, output.as_deref()).await,
+        Command::Rm { fid } => rm(&master, fid).await,
+        Command::Stat { fid } => stat(&master, fid).await,
+        Command::Grow { count, placement } => grow(&master, *count, placement).await,
+        Command::Vacuum {
+            garbage_threshold,
+            collection,
+            volume_id,
+        } => {
+            let options = VacuumVolumeOptions {
+                garbage_threshold: *garbage_threshold,
+                collection: collection.clone(),
+                volume_id: *volume_id,
+            };
+            let resp = master.vacuum_volumes(&Some(options)).await?;
+            Ok(Output::new(json!(resp.topology), "vacuum finished"))
+        }
+        Command::Topology => topology(&master).await,
+    }
+}
+
+fn assign_options(count: Option<u32>, placement: &Placement) -> AssignKeyOptions {
+    AssignKeyOptions {
+        count,
+        collection: placement.collection.clone(),
+        replication: placement.replication.clone(),
+        ttl: placement.ttl.clone(),
+        data_center: placement.data_center.clone(),
+        ..Default::default()
+    }
+}
+
+async fn assign(master: &Master, count: Option<u32>, placement: &Placement) -> CliResult {
+    let resp = master
+        .assign_key(&Some(assign_options(count, placement)))
+        .await?;
+
+    let fids: Vec<FID> = resp.fids().map(|a| a.fid).collect();
+    let text = fids
+        .iter()
+        .map(|fid| format!("{} {}", fid.to_string(), resp.location.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(Output::new(
+        json!({
+            "fid": resp.fid,
+            "fids": fids,
+            "url": resp.location.url,
+            "publicUrl": resp.location.public_url,
+            "count": resp.count,
+        }),
+        text,
+    ))
+}
+
+/// Accepts a volume id like 3 or a file id like 3,01637037d6
+async fn lookup(master: &Master, id: &str) -> CliResult {
+    let volume_id: u32 = match id.contains(',') {
+        true => id.parse::<FID>()?.volume_id,
+        false => id.parse()?,
+    };
+
+    let locations = locations(master, volume_id).await?;
+    let text = locations
+        .iter()
+        .map(|l| format!("{} {}", l.url, l.public_url))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(Output::new(
+        json!({ "volumeId": volume_id, "locations": locations }),
+        text,
+    ))
+}
+
+async fn locations(master: &Master, volume_id: u32) -> Result<Vec<Location>, Box<dyn Error>> {
+    let options = LookupVolumeOptions {
+        read: Some(true),
+        ..Default::default()
+    };
+
+    Ok(master
+        .lookup_volume_id(volume_id, &Some(options))
+        .await?
+        .locations)
+}
+
+async fn put(master: &Master, file: &Path, placement: &Placement) -> CliResult {
+    let assigned = master
+        .assign_key(&Some(assign_options(None, placement)))
+        .await?;
+    let volume = master.client().volume_from_str(&assigned.location.url)?;
+
+    let options = UploadFileOptions {
+        jwt: assigned.auth,
+        ..Default::default()
+    };
+    let resp = volume
+        .upload_path(&assigned.fid, file, &Some(options))
+        .await?;
+
+    Ok(Output::new(
+        json!({ "fid": assigned.fid, "url": assigned.location.url, "size": resp.size, "eTag": resp.e_tag }),
+        assigned.fid.to_string(),
+    ))
+}
+
+/// Streams the file from the first location that has it to the output file or stdout without buffering it in memory
+async fn get(master: &Master, fid: &FID, output: Option<&Path>) -> CliResult {
+    let mut last_err: Option<Box<dyn Error>> = None;
+
+    for location in locations(master, fid.volume_id).await? {
+        let volume = master.client().volume_from_str(&location.url)?;
+
+        // only a failed request moves on to the next replica, errors while copying abort
+        let mut reader = match volume.get_file_stream(fid, &None).await {
+            Ok(stream) => stream.into_reader(),
+            Err(err) => {
+                last_err = Some(err.into());
+                continue;
+            }
+        };
+
+        return match output {
+            Some(path) => {
+                let size = write_file(&mut reader, path).await?;
+
+                Ok(Output::new(
+                    json!({ "fid": fid, "file": path, "size": size }),
+                    "",
+                ))
+            }
+            None => {
+                let mut stdout = tokio::io::stdout();
+                tokio::io::copy(&mut reader, &mut stdout).await?;
+                stdout.flush().await?;
+
+                Ok(Output::new(Value::Null, ""))
+            }
+        };
+    }
+
+    Err(last_err.unwrap_or_else(|| "volume has no locations".into()))
+}
+
+/// Writes to a temporary file next to the path and renames it once complete,
+/// so a failed download never leaves a truncated file behind
+async fn write_file(reader: &mut (impl AsyncRead + Unpin), path: &Path) -> io::Result<u64> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.part", name, std::process::id()));
+
+    let written = async {
+        let mut file = tokio::fs::File::create(&temp).await?;
+        let size = tokio::io::copy(reader, &mut file).await?;
+        file.flush().await?;
+        tokio::fs::rename(&temp, path).await?;
+        Ok(size)
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    written
+}
+
+/// Deletes through the first location that answers, the volume server removes the other replicas
+async fn rm(master: &Master, fid: &FID) -> CliResult {
+    let mut last_err: Option<Box<dyn Error>> = None;
+
+    for location in locations(master, fid.volume_id).await? {
+        let volume = master.client().volume_from_str(&location.url)?;
+
+        match volume.delete_file(fid).await {
+            Ok(resp) => {
+                return Ok(Output::new(
+                    json!({ "fid": fid, "size": resp.size }),
+                    format!("deleted {} ({} bytes)", fid.to_string(), resp.size),
+                ))
+            }
+            Err(err) => last_err = Some(err.into()),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| "volume has no locations".into()))
+}
+
+/// Reads the metadata from the first location that answers
+async fn stat(master: &Master, fid: &FID) -> CliResult {
+    let mut last_err: Option<Box<dyn Error>> = None;
+
+    for location in locations(master, fid.volume_id).await? {
+        let volume = master.client().volume_from_str(&location.url)?;
+
+        match volume.head_file(fid, &None).await {
+            Ok(meta) => return Ok(stat_output(fid, &location, meta)),
+            Err(err) => last_err = Some(err.into()),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| "volume has no locations".into()))
+}
+
+fn stat_output(fid: &FID, location: &Location, meta: FileMeta) -> Output {
+    let last_modified = meta
+        .last_modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    let mut text = vec![
+        format!("fid: {}", fid.to_string()),
+        format!("url: {}", location.url),
+        format!("size: {}", display(&meta.size)),
+        format!("etag: {}", display(&meta.e_tag)),
+        format!("content type: {}", display(&meta.content_type)),
+        format!("file name: {}", display(&meta.file_name)),
+        format!("last modified: {}", display(&meta.last_modified.map(httpdate::fmt_http_date))),
+    ];
+    let mut pairs: Vec<_> = meta.pairs.iter().collect();
+    pairs.sort();
+    text.extend(pairs.iter().map(|(k, v)| format!("{}: {}", k, v)));
+
+    Output::new(
+        json!({
+            "fid": fid,
+            "url": location.url,
+            "size": meta.size,
+            "eTag": meta.e_tag,
+            "contentType": meta.content_type,
+            "fileName": meta.file_name,
+            "lastModified": last_modified,
+            "pairs": meta.pairs,
+        }),
+        text.join("\n"),
+    )
+}
+
+fn display<T: ToString>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+async fn grow(master: &Master, count: Option<u32>, placement: &Placement) -> CliResult {
+    let options = GrowVolumeOptions {
+        count,
+        collection: placement.collection.clone(),
+        replication: placement.replication.clone(),
+        ttl: placement.ttl.clone(),
+        data_center: placement.data_center.clone(),
+        ..Default::default()
+    };
+    let resp = master.grow_volume(&Some(options)).await?;
+
+    Ok(Output::new(
+        json!({ "count": resp.count }),
+        format!("created {} volumes", resp.count),
+    ))
+}
+
+async fn topology(master: &Master) -> CliResult {
+    let topology = master.topology().await?;
+    let mut text = vec![format!("free {} of {} volume slots", topology.free, topology.max)];
+
+    for dc in &topology.data_centers {
+        text.push(format!("{} free {}/{}", dc.id, dc.free, dc.max));
+        for rack in &dc.racks {
+            text.push(format!("  {} free {}/{}", rack.id, rack.free, rack.max));
+            for node in &rack.data_nodes {
+                text.push(format!("    {} free {}/{}", node.url, node.free, node.max));
+                for v in &node.volumes {
+                    text.push(format!(
+                        "      volume {} collection {:?} size {} files {} deleted {}{}",
+                        v.id,
+                        v.collection,
+                        v.size,
+                        v.file_count,
+                        v.delete_count,
+                        if v.read_only { " read only" } else { "" }
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(Output::new(json!(topology), text.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+
+    use super::{Cli, Command};
+
+    #[test]
+    fn parse_commands() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["weed-rs", "put", "a.txt", "-r", "010", "--ttl", "3d", "--json"]).unwrap();
+        assert!(cli.json);
+        match cli.command {
+            Command::Put { placement, .. } => {
+                assert_eq!("010", placement.replication.unwrap().to_string());
+                assert_eq!("3d", placement.ttl.unwrap().to_string());
+            }
+            _ => panic!("expected put"),
+        }
+
+        let cli = Cli::try_parse_from(["weed-rs", "-m", "10.0.0.1:9333", "get", "3,01637037d6", "-o", "out"]).unwrap();
+        assert_eq!("10.0.0.1:9333", cli.master);
+
+        assert!(Cli::try_parse_from(["weed-rs", "rm", "not-a-fid"]).is_err());
+        assert!(Cli::try_parse_from(["weed-rs", "assign", "-r", "4"]).is_err());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn stat_and_rm_fall_over_to_next_replica() {
+        use bytes::Bytes;
+        use rusty_weed::testing::MockServer;
+
+        let server = MockServer::start().await;
+        let master = server.master();
+        let assigned = master.assign_key(&None).await.unwrap();
+        server
+            .volume()
+            .upload_file_bytes(&assigned.fid, &Bytes::from("Hello World!"), &None)
+            .await
+            .unwrap();
+
+        // nothing listens on the first replica
+        let dead = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let url = format!("{}:{}", server.host(), server.port());
+        server.set_locations(&[dead, url.clone()]);
+
+        let output = super::stat(&master, &assigned.fid).await.unwrap();
+        assert_eq!(url, output.json["url"]);
+        assert_eq!(12, output.json["size"]);
+
+        super::rm(&master, &assigned.fid).await.unwrap();
+        assert!(!server.contains(&assigned.fid));
+    }
+}
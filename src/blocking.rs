@@ -0,0 +1,285 @@
+use std::{fmt, future::Future, path::Path, str::FromStr, sync::OnceLock};
+
+use bytes::Bytes;
+use tokio::runtime::{Handle, Runtime};
+
+use crate::{
+    client::WeedClient,
+    master::{
+        self, AssignKeyOptions, AssignKeyResponse, LookupVolumeOptions, LookupVolumeResponse,
+        MasterErrors,
+    },
+    utils::FID,
+    volume::{
+        self, DeleteFileOptions, DeleteResponse, FileMeta, GetFileOptions, UploadFileOptions,
+        UploadResponse, VolumeErrors,
+    },
+};
+
+/// Runs a future to completion on the runtime shared by all blocking handles
+///
+/// Panics when called from within an async runtime, blocking there would stall its workers
+fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    if Handle::try_current().is_ok() {
+        panic!(
+            "rusty_weed::blocking must not be used from within an async runtime, \
+             use rusty_weed::master and rusty_weed::volume there"
+        );
+    }
+
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("rusty-weed-blocking")
+                .enable_all()
+                .build()
+                .expect("failed to start the runtime of the blocking client")
+        })
+        .block_on(future)
+}
+
+/// Blocking version of [master::Master]
+///
+/// Requests run on a small runtime owned by the crate, callers do not need tokio.
+/// Like the blocking client of reqwest it must not be used from within an async runtime,
+/// calls from there panic.
+///
+/// # Example
+/// ```no_run
+/// use bytes::Bytes;
+/// use rusty_weed::blocking::Master;
+///
+/// let master = Master::new("localhost", Some(9333));
+/// let assigned = master.assign_key(&None).unwrap();
+///
+/// let volume = master.volume_from_str(&assigned.location.url).unwrap();
+/// volume.upload_file_bytes(&assigned.fid, &Bytes::from("Hello World!"), &None).unwrap();
+///
+/// let data = volume.get_file_bytes(&assigned.fid, &None).unwrap();
+/// volume.delete_file(&assigned.fid).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Master {
+    inner: master::Master,
+}
+
+impl From<master::Master> for Master {
+    fn from(inner: master::Master) -> Self {
+        Master { inner }
+    }
+}
+
+impl fmt::Display for Master {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl FromStr for Master {
+    type Err = MasterErrors;
+
+    fn from_str(s: &str) -> Result<Master, MasterErrors> {
+        Ok(Master::from(master::Master::from_str(s)?))
+    }
+}
+
+impl Master {
+    pub fn new(host: &str, port: Option<u16>) -> Master {
+        Master::from(master::Master::new(host, port))
+    }
+
+    /// Creates a master using the given shared client
+    pub fn with_client(host: &str, port: Option<u16>, client: WeedClient) -> Master {
+        Master::from(master::Master::with_client(host, port, client))
+    }
+
+    /// The async master, sharing the same client
+    pub fn inner(&self) -> &master::Master {
+        &self.inner
+    }
+
+    /// Creates a volume from a location url sharing the client of the master
+    pub fn volume_from_str(&self, s: &str) -> Result<Volume, VolumeErrors> {
+        Ok(Volume::from(self.inner.client.volume_from_str(s)?))
+    }
+
+    /// See [assign_key](master::Master::assign_key)
+    pub fn assign_key(
+        &self,
+        options: &Option<AssignKeyOptions>,
+    ) -> Result<AssignKeyResponse, MasterErrors> {
+        block_on(self.inner.assign_key(options))
+    }
+
+    /// See [lookup_volume](master::Master::lookup_volume)
+    pub fn lookup_volume(
+        &self,
+        volume_id: &FID,
+        options: &Option<LookupVolumeOptions>,
+    ) -> Result<LookupVolumeResponse, MasterErrors> {
+        block_on(self.inner.lookup_volume(volume_id, options))
+    }
+
+    /// See [lookup_volume_id](master::Master::lookup_volume_id)
+    pub fn lookup_volume_id(
+        &self,
+        volume_id: u32,
+        options: &Option<LookupVolumeOptions>,
+    ) -> Result<LookupVolumeResponse, MasterErrors> {
+        block_on(self.inner.lookup_volume_id(volume_id, options))
+    }
+}
+
+/// Blocking version of [volume::Volume]
+#[derive(Clone, Debug)]
+pub struct Volume {
+    inner: volume::Volume,
+}
+
+impl From<volume::Volume> for Volume {
+    fn from(inner: volume::Volume) -> Self {
+        Volume { inner }
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl FromStr for Volume {
+    type Err = VolumeErrors;
+
+    fn from_str(s: &str) -> Result<Volume, VolumeErrors> {
+        Ok(Volume::from(volume::Volume::from_str(s)?))
+    }
+}
+
+impl Volume {
+    pub fn new(host: &str, port: Option<u16>) -> Volume {
+        Volume::from(volume::Volume::new(host, port))
+    }
+
+    /// Creates a volume using the given shared client
+    pub fn with_client(host: &str, port: Option<u16>, client: WeedClient) -> Volume {
+        Volume::from(volume::Volume::with_client(host, port, client))
+    }
+
+    /// The async volume, sharing the same client
+    pub fn inner(&self) -> &volume::Volume {
+        &self.inner
+    }
+
+    /// See [get_file_bytes](volume::Volume::get_file_bytes)
+    pub fn get_file_bytes(
+        &self,
+        fid: &FID,
+        options: &Option<GetFileOptions>,
+    ) -> Result<Bytes, VolumeErrors> {
+        block_on(self.inner.get_file_bytes(fid, options))
+    }
+
+    /// See [head_file](volume::Volume::head_file)
+    pub fn head_file(
+        &self,
+        fid: &FID,
+        options: &Option<GetFileOptions>,
+    ) -> Result<FileMeta, VolumeErrors> {
+        block_on(self.inner.head_file(fid, options))
+    }
+
+    /// See [upload_file_bytes](volume::Volume::upload_file_bytes)
+    pub fn upload_file_bytes(
+        &self,
+        fid: &FID,
+        data: &Bytes,
+        options: &Option<UploadFileOptions>,
+    ) -> Result<UploadResponse, VolumeErrors> {
+        block_on(self.inner.upload_file_bytes(fid, data, options))
+    }
+
+    /// See [upload_path](volume::Volume::upload_path)
+    pub fn upload_path(
+        &self,
+        fid: &FID,
+        path: &Path,
+        options: &Option<UploadFileOptions>,
+    ) -> Result<UploadResponse, VolumeErrors> {
+        block_on(self.inner.upload_path(fid, path, options))
+    }
+
+    /// See [delete_file](volume::Volume::delete_file)
+    pub fn delete_file(&self, fid: &FID) -> Result<DeleteResponse, VolumeErrors> {
+        block_on(self.inner.delete_file(fid))
+    }
+
+    /// See [delete_file_with](volume::Volume::delete_file_with)
+    pub fn delete_file_with(
+        &self,
+        fid: &FID,
+        options: &Option<DeleteFileOptions>,
+    ) -> Result<DeleteResponse, VolumeErrors> {
+        block_on(self.inner.delete_file_with(fid, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use bytes::Bytes;
+
+    use crate::testing::MockServer;
+
+    use super::Master;
+
+    #[test]
+    fn runs_without_runtime() {
+        // nothing listens on port 1, the request has to fail instead of panicking
+        let master = Master::new("127.0.0.1", Some(1));
+        assert!(master.assign_key(&None).is_err());
+
+        let handle = thread::spawn(move || master.assign_key(&None).is_err());
+        assert!(handle.join().unwrap());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "rusty_weed::blocking must not be used from within an async runtime")]
+    async fn panics_within_runtime() {
+        let master = Master::new("127.0.0.1", Some(1));
+        let _ = master.assign_key(&None);
+    }
+
+    #[test]
+    fn round_trip() {
+        // the mock is served by a worker thread of its own runtime, the test thread stays plain
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = runtime.block_on(MockServer::start());
+
+        let master = Master::from(server.master());
+        let assigned = master.assign_key(&None).unwrap();
+        let volume = master.volume_from_str(&assigned.location.url).unwrap();
+
+        let data = Bytes::from("Hello World!");
+        let uploaded = volume
+            .upload_file_bytes(&assigned.fid, &data, &None)
+            .unwrap();
+        assert_eq!(data.len(), uploaded.size);
+
+        assert_eq!(data, volume.get_file_bytes(&assigned.fid, &None).unwrap());
+        let meta = volume.head_file(&assigned.fid, &None).unwrap();
+        assert_eq!(Some(data.len() as u64), meta.size);
+
+        volume.delete_file(&assigned.fid).unwrap();
+        assert!(!server.contains(&assigned.fid));
+        assert!(volume.get_file_bytes(&assigned.fid, &None).is_err());
+    }
+}
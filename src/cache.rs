@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::utils::Location;
+
+/// Result of a [LocationCache] lookup
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachedLocations {
+    /// Locations returned by the master
+    Found(Vec<Location>),
+    /// The master did not know the volume, cached for the negative ttl
+    Missing,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: CachedLocations,
+    expires: Instant,
+}
+
+/// Cache mapping volume ids to their locations
+///
+/// Cloning is cheap, all clones share the same entries.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use rusty_weed::cache::LocationCache;
+///
+/// let cache = LocationCache::new(Duration::from_secs(60), Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LocationCache {
+    /// How long found locations are kept
+    pub ttl: Duration,
+    /// How long unknown volumes are kept, zero disables negative caching
+    pub negative_ttl: Duration,
+    entries: Arc<RwLock<HashMap<u32, CacheEntry>>>,
+}
+
+impl Default for LocationCache {
+    /// Caches locations for 10 minutes and unknown volumes for 10 seconds
+    fn default() -> Self {
+        LocationCache::new(Duration::from_secs(600), Duration::from_secs(10))
+    }
+}
+
+impl LocationCache {
+    pub fn new(ttl: Duration, negative_ttl: Duration) -> LocationCache {
+        LocationCache {
+            ttl,
+            negative_ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns the cached locations of a volume if they are not expired
+    pub fn get(&self, volume_id: u32) -> Option<CachedLocations> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+
+        match entries.get(&volume_id) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Caches the locations of a volume
+    pub fn insert(&self, volume_id: u32, locations: Vec<Location>) {
+        self.put(volume_id, CachedLocations::Found(locations), self.ttl);
+    }
+
+    /// Remembers that the master does not know a volume
+    pub fn insert_missing(&self, volume_id: u32) {
+        if !self.negative_ttl.is_zero() {
+            self.put(volume_id, CachedLocations::Missing, self.negative_ttl);
+        }
+    }
+
+    /// Removes a volume, the next read asks the master again
+    pub fn invalidate(&self, volume_id: u32) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&volume_id);
+    }
+
+    /// Removes all volumes
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+    }
+
+    fn put(&self, volume_id: u32, value: CachedLocations, ttl: Duration) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        entries.retain(|_, entry| entry.expires > now);
+        entries.insert(
+            volume_id,
+            CacheEntry {
+                value,
+                expires: now + ttl,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::utils::Location;
+
+    use super::{CachedLocations, LocationCache};
+
+    fn location() -> Location {
+        Location {
+            public_url: "1.1.1.1:8080".to_string(),
+            url: "1.1.1.1:8080".to_string(),
+            data_center: None,
+        }
+    }
+
+    #[test]
+    fn insert_and_invalidate() {
+        let cache = LocationCache::default();
+
+        assert_eq!(None, cache.get(3));
+
+        cache.insert(3, vec![location()]);
+        assert_eq!(Some(CachedLocations::Found(vec![location()])), cache.get(3));
+
+        cache.invalidate(3);
+        assert_eq!(None, cache.get(3));
+    }
+
+    #[test]
+    fn entries_expire() {
+        let cache = LocationCache::new(Duration::ZERO, Duration::from_secs(60));
+
+        cache.insert(3, vec![location()]);
+        assert_eq!(None, cache.get(3));
+
+        cache.insert_missing(4);
+        assert_eq!(Some(CachedLocations::Missing), cache.get(4));
+    }
+
+    #[test]
+    fn negative_caching_disabled() {
+        let cache = LocationCache::new(Duration::from_secs(60), Duration::ZERO);
+
+        cache.insert_missing(4);
+        assert_eq!(None, cache.get(4));
+    }
+}
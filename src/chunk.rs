@@ -0,0 +1,112 @@
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{master::AssignKeyOptions, utils::FID};
+
+/// Manifest of a file split into chunks, uploaded with `cm=true`
+///
+/// Reading the manifest file id from a volume server returns the joined chunks.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ChunkManifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    /// Size of the whole file
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub chunks: Vec<ChunkInfo>,
+}
+
+/// A single chunk of a [ChunkManifest]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChunkInfo {
+    pub fid: FID,
+    /// Position of the chunk in the whole file
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Options for the store function [put_chunked](crate::store::Store::put_chunked)
+#[derive(Debug, Clone)]
+pub struct ChunkedPutOptions {
+    /// Size of a chunk in bytes, must stay below the max upload size of the volume servers
+    pub chunk_size: usize,
+    /// Number of chunks uploaded at the same time, also the number of chunks held in memory
+    pub concurrency: usize,
+    /// File name stored in the manifest
+    pub name: Option<String>,
+    /// Mime type stored in the manifest
+    pub mime: Option<String>,
+    /// Used for every assignment, the count is set per batch of chunks
+    pub assign: Option<AssignKeyOptions>,
+}
+
+impl Default for ChunkedPutOptions {
+    /// Chunks of 8 MiB with 4 uploads at the same time
+    fn default() -> Self {
+        ChunkedPutOptions {
+            chunk_size: 8 * 1024 * 1024,
+            concurrency: 4,
+            name: None,
+            mime: None,
+            assign: None,
+        }
+    }
+}
+
+/// Reads until the chunk is full or the reader is exhausted,
+/// an empty chunk means there is nothing left to read
+pub(crate) async fn read_chunk<R>(reader: &mut R, chunk_size: usize) -> std::io::Result<Bytes>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = BytesMut::with_capacity(chunk_size);
+
+    while buf.len() < chunk_size {
+        let mut limited = (&mut *reader).take((chunk_size - buf.len()) as u64);
+        if limited.read_buf(&mut buf).await? == 0 {
+            break;
+        }
+    }
+
+    Ok(buf.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_chunk, ChunkManifest};
+
+    #[tokio::test]
+    async fn splits_into_chunks() {
+        let data = b"Hello World!".to_vec();
+        let mut reader = data.as_slice();
+
+        assert_eq!(&b"Hello"[..], read_chunk(&mut reader, 5).await.unwrap());
+        assert_eq!(&b" Worl"[..], read_chunk(&mut reader, 5).await.unwrap());
+        assert_eq!(&b"d!"[..], read_chunk(&mut reader, 5).await.unwrap());
+        assert!(read_chunk(&mut reader, 5).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let data = r#"{
+            "name": "video.mp4",
+            "mime": "video/mp4",
+            "size": 12,
+            "chunks": [
+                {"fid": "3,01637037d6", "offset": 0, "size": 8},
+                {"fid": "3,01637037d6_1", "offset": 8, "size": 4}
+            ]
+        }"#;
+
+        let manifest = serde_json::from_str::<ChunkManifest>(data).unwrap();
+        assert_eq!(2, manifest.chunks.len());
+        assert_eq!("3,01637037d6_1", manifest.chunks[1].fid.to_string());
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(json.contains(r#""fid":"3,01637037d6_1""#));
+    }
+}
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use reqwest::{header::HeaderMap, Proxy};
+use thiserror::Error;
+
+use crate::{
+    cluster::MasterCluster,
+    filer::{Filer, FilerErrors},
+    master::{Master, MasterErrors},
+    retry::RetryPolicy,
+    volume::{Volume, VolumeErrors},
+};
+
+#[derive(Error, Debug)]
+pub enum ClientErrors {
+    #[error("reqwest error")]
+    ReqwestError(#[from] reqwest::Error),
+}
+
+/// Shared http client for all SeaweedFS servers
+///
+/// Holds a single connection pooled reqwest client. Cloning is cheap and every
+/// [master](crate::master::Master), [volume](crate::volume::Volume) and [filer](crate::filer::Filer)
+/// handed out by it reuses the same pool, keep-alive connections and TLS sessions.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use rusty_weed::client::WeedClient;
+///
+/// let client = WeedClient::builder()
+///     .timeout(Duration::from_secs(30))
+///     .pool_max_idle_per_host(32)
+///     .build()
+///     .unwrap();
+///
+/// let master = client.master("localhost", Some(9333));
+/// let volume = client.volume_from_str("localhost:8080").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WeedClient {
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl WeedClient {
+    /// Creates a client with the default reqwest settings
+    pub fn new() -> WeedClient {
+        Default::default()
+    }
+
+    /// Creates a builder to configure the underlying http client
+    pub fn builder() -> WeedClientBuilder {
+        Default::default()
+    }
+
+    /// Wraps an already configured reqwest client
+    pub fn from_reqwest(http: reqwest::Client) -> WeedClient {
+        WeedClient {
+            http,
+            retry: Default::default(),
+        }
+    }
+
+    /// The underlying reqwest client
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// Retry policy applied to master, volume and filer requests
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Returns a client sharing the connection pool with another retry policy
+    pub fn with_retry_policy(&self, retry: RetryPolicy) -> WeedClient {
+        WeedClient {
+            http: self.http.clone(),
+            retry,
+        }
+    }
+
+    /// Creates a master sharing this client
+    pub fn master(&self, host: &str, port: Option<u16>) -> Master {
+        Master::with_client(host, port, self.clone())
+    }
+
+    /// Creates a master sharing this client from a string like 0.0.0.0:9333
+    pub fn master_from_str(&self, s: &str) -> Result<Master, MasterErrors> {
+        let mut master: Master = s.parse()?;
+        master.client = self.clone();
+        Ok(master)
+    }
+
+    /// Creates a cluster of masters sharing this client from strings like 0.0.0.0:9333
+    pub fn master_cluster(&self, peers: &[&str]) -> Result<MasterCluster, MasterErrors> {
+        let mut masters = Vec::with_capacity(peers.len());
+        for peer in peers {
+            masters.push(self.master_from_str(peer)?);
+        }
+
+        Ok(MasterCluster::new(masters))
+    }
+
+    /// Creates a volume sharing this client
+    pub fn volume(&self, host: &str, port: Option<u16>) -> Volume {
+        Volume::with_client(host, port, self.clone())
+    }
+
+    /// Creates a volume sharing this client from a string like 0.0.0.0:8080
+    ///
+    /// Should be used in combination with [locations](crate::utils::Location) received from [looking up a volume](crate::master::Master::lookup_volume)
+    pub fn volume_from_str(&self, s: &str) -> Result<Volume, VolumeErrors> {
+        let mut volume: Volume = s.parse()?;
+        volume.client = self.clone();
+        Ok(volume)
+    }
+
+    /// Creates a filer sharing this client
+    pub fn filer(&self, host: &str, port: Option<u16>) -> Filer {
+        Filer::with_client(host, port, self.clone())
+    }
+
+    /// Creates a filer sharing this client from a string like 0.0.0.0:8888
+    pub fn filer_from_str(&self, s: &str) -> Result<Filer, FilerErrors> {
+        let mut filer: Filer = s.parse()?;
+        filer.client = self.clone();
+        Ok(filer)
+    }
+}
+
+/// Builder for a [WeedClient]
+#[derive(Debug, Default)]
+pub struct WeedClientBuilder {
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    proxies: Vec<Proxy>,
+    user_agent: Option<String>,
+    default_headers: HeaderMap,
+    retry: RetryPolicy,
+}
+
+impl WeedClientBuilder {
+    /// Total timeout of a request, from connecting until the body has been read
+    pub fn timeout(mut self, timeout: Duration) -> WeedClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for establishing a connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> WeedClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// How long idle connections are kept in the pool
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> WeedClientBuilder {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Max number of idle connections kept per host
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> WeedClientBuilder {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Adds a proxy, can be called multiple times
+    pub fn proxy(mut self, proxy: Proxy) -> WeedClientBuilder {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Sets the User-Agent header sent with every request
+    pub fn user_agent(mut self, user_agent: &str) -> WeedClientBuilder {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Headers sent with every request
+    pub fn default_headers(mut self, headers: HeaderMap) -> WeedClientBuilder {
+        self.default_headers = headers;
+        self
+    }
+
+    /// Retry policy for master, volume and filer requests, no retries by default
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> WeedClientBuilder {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<WeedClient, ClientErrors> {
+        let mut builder = reqwest::Client::builder()
+            .gzip(true)
+            .default_headers(self.default_headers);
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+
+        for proxy in self.proxies {
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        Ok(WeedClient {
+            http: builder.build()?,
+            retry: self.retry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::WeedClient;
+
+    #[test]
+    fn handles_share_client() {
+        let client = WeedClient::builder()
+            .timeout(Duration::from_secs(5))
+            .user_agent("rusty_weed-test")
+            .build()
+            .unwrap();
+
+        let master = client.master("localhost", Some(9333));
+        let volume = client.volume_from_str("1.1.1.1:8080").unwrap();
+
+        assert_eq!("http://localhost:9333", master.to_string());
+        assert_eq!("http://1.1.1.1:8080", volume.to_string());
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    client::WeedClient,
+    master::{
+        AssignKeyOptions, AssignKeyResponse, LookupVolumeOptions, LookupVolumeResponse, Master,
+        MasterErrors,
+    },
+    utils::FID,
+};
+
+/// Several masters running in raft
+///
+/// Requests go to the last known leader first, if a master can not be reached
+/// or fails with a 5xx the next peer is tried. Followers redirect or proxy
+/// requests to the leader themselves, reqwest follows those redirects.
+///
+/// # Example
+/// ```
+/// use rusty_weed::client::WeedClient;
+///
+/// let client = WeedClient::new();
+/// let cluster = client
+///     .master_cluster(&["10.0.0.1:9333", "10.0.0.2:9333", "10.0.0.3:9333"])
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct MasterCluster {
+    pub peers: Vec<Master>,
+    leader: Arc<RwLock<Option<Master>>>,
+    client: WeedClient,
+}
+
+impl From<Master> for MasterCluster {
+    fn from(master: Master) -> Self {
+        MasterCluster::new(vec![master])
+    }
+}
+
+impl MasterCluster {
+    pub fn new(peers: Vec<Master>) -> MasterCluster {
+        let client = match peers.first() {
+            Some(master) => master.client.clone(),
+            None => WeedClient::new(),
+        };
+
+        MasterCluster {
+            peers,
+            leader: Default::default(),
+            client,
+        }
+    }
+
+    /// The client of the first peer, used for volumes of this cluster
+    pub fn client(&self) -> &WeedClient {
+        &self.client
+    }
+
+    /// The last known leader
+    pub fn leader(&self) -> Option<Master> {
+        self.leader
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_leader(&self, master: Option<Master>) {
+        *self.leader.write().unwrap_or_else(|e| e.into_inner()) = master;
+    }
+
+    fn is_leader(&self, master: &Master) -> bool {
+        self.leader()
+            .is_some_and(|leader| leader.to_string() == master.to_string())
+    }
+
+    /// Asks the peers for the current leader via /cluster/status
+    pub async fn discover_leader(&self) -> Result<Master, MasterErrors> {
+        let mut last_err = MasterErrors::NoMasterAvailable;
+
+        for peer in &self.peers {
+            let status = match peer.cluster_status().await {
+                Ok(status) => status,
+                Err(err) => {
+                    last_err = err;
+                    continue;
+                }
+            };
+
+            let leader = match status.is_leader || status.leader.is_empty() {
+                true => peer.clone(),
+                false => {
+                    let mut leader: Master = status.leader_address().parse()?;
+                    leader.client = peer.client.clone();
+                    leader
+                }
+            };
+
+            self.set_leader(Some(leader.clone()));
+            return Ok(leader);
+        }
+
+        Err(last_err)
+    }
+
+    /// Assigns a file id on the leader or any other reachable master
+    pub async fn assign_key(
+        &self,
+        options: &Option<AssignKeyOptions>,
+    ) -> Result<AssignKeyResponse, MasterErrors> {
+        self.call(|master| async move { master.assign_key(options).await })
+            .await
+    }
+
+    /// Lookup the locations of a volume on the leader or any other reachable master
+    pub async fn lookup_volume(
+        &self,
+        volume_id: &FID,
+        options: &Option<LookupVolumeOptions>,
+    ) -> Result<LookupVolumeResponse, MasterErrors> {
+        self.call(|master| async move { master.lookup_volume(volume_id, options).await })
+            .await
+    }
+
+    /// Lookup the locations of a volume by its id on the leader or any other reachable master
+    pub async fn lookup_volume_id(
+        &self,
+        volume_id: u32,
+        options: &Option<LookupVolumeOptions>,
+    ) -> Result<LookupVolumeResponse, MasterErrors> {
+        self.call(|master| async move { master.lookup_volume_id(volume_id, options).await })
+            .await
+    }
+
+    /// Runs the operation on the leader first and falls over to the other peers
+    ///
+    /// A leader that can not be reached is forgotten and discovered again by the next call
+    pub async fn call<T, F, Fut>(&self, op: F) -> Result<T, MasterErrors>
+    where
+        F: Fn(Master) -> Fut,
+        Fut: Future<Output = Result<T, MasterErrors>>,
+    {
+        if self.leader().is_none() {
+            let _ = self.discover_leader().await;
+        }
+
+        let mut last_err = MasterErrors::NoMasterAvailable;
+
+        for master in self.candidates() {
+            match op(master.clone()).await {
+                // any peer may answer, only discovery decides who the leader is
+                Ok(resp) => return Ok(resp),
+                Err(err) if is_unavailable(&err) => {
+                    if self.is_leader(&master) {
+                        self.set_leader(None);
+                    }
+                    last_err = err;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    /// The leader followed by all peers that are not the leader
+    fn candidates(&self) -> Vec<Master> {
+        let mut candidates = Vec::with_capacity(self.peers.len() + 1);
+
+        if let Some(leader) = self.leader() {
+            candidates.push(leader);
+        }
+
+        for peer in &self.peers {
+            if !candidates.iter().any(|c| c.to_string() == peer.to_string()) {
+                candidates.push(peer.clone());
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Whether the master could not handle the request and another peer should be asked
+fn is_unavailable(err: &MasterErrors) -> bool {
+    match err {
+        MasterErrors::ReqwestError(e) => e.is_connect() || e.is_timeout(),
+        MasterErrors::ServerError(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{client::WeedClient, retry::RetryPolicy, testing::MockServer};
+
+    use super::MasterCluster;
+
+    /// Address nothing listens on
+    fn dead_address() -> (String, u16) {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        (addr.ip().to_string(), addr.port())
+    }
+
+    #[test]
+    fn leader_first_in_candidates() {
+        let client = WeedClient::new();
+        let cluster = client
+            .master_cluster(&["10.0.0.1:9333", "10.0.0.2:9333", "10.0.0.3:9333"])
+            .unwrap();
+
+        cluster.set_leader(Some(client.master("10.0.0.2", Some(9333))));
+
+        let candidates: Vec<String> = cluster.candidates().iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            vec![
+                "http://10.0.0.2:9333",
+                "http://10.0.0.1:9333",
+                "http://10.0.0.3:9333"
+            ],
+            candidates
+        );
+    }
+
+    #[tokio::test]
+    async fn no_peer_reachable() {
+        let client = WeedClient::new();
+        // nothing listens on port 1, the cluster has to give up on every peer
+        let cluster = client
+            .master_cluster(&["127.0.0.1:1", "127.0.0.2:1"])
+            .unwrap();
+
+        let resp = cluster.assign_key(&None).await;
+        assert!(resp.is_err());
+        assert!(cluster.leader().is_none());
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_next_peer() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy::exponential(1))
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client.clone()).await;
+        let (host, port) = dead_address();
+        let dead = client.master(&host, Some(port));
+        let cluster = MasterCluster::new(vec![dead.clone(), server.master()]);
+
+        // the master still known as leader went away
+        cluster.set_leader(Some(dead));
+        cluster.assign_key(&None).await.unwrap();
+        assert_eq!(1, server.requests());
+        assert!(cluster.leader().is_none());
+
+        cluster.assign_key(&None).await.unwrap();
+        assert_eq!(
+            Some(server.master().to_string()),
+            cluster.leader().map(|m| m.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn discovers_leader_through_follower() {
+        let follower = MockServer::start().await;
+        let leader = MockServer::start().await;
+        follower.follow(&format!("{}:{}", leader.host(), leader.port()));
+        let cluster = MasterCluster::new(vec![follower.master(), leader.master()]);
+
+        cluster.assign_key(&None).await.unwrap();
+        cluster.assign_key(&None).await.unwrap();
+
+        assert_eq!(
+            Some(leader.master().to_string()),
+            cluster.leader().map(|m| m.to_string())
+        );
+        // the follower was only asked for the leader, both assigns went to the leader
+        assert_eq!(1, follower.requests());
+        assert_eq!(2, leader.requests());
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::retry::{ErrorClass, RetryPolicy, Retryable};
+
+/// A response of a SeaweedFS server with an unexpected StatusCode
+///
+/// Carried by the errors of the [master](crate::master::MasterErrors),
+/// [volume](crate::volume::VolumeErrors) and [filer](crate::filer::FilerErrors).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseError {
+    /// What the request tried to do, like assign or upload
+    pub operation: &'static str,
+    pub url: String,
+    pub status: u16,
+    /// The message of a {"error": "..."} body or the raw body otherwise
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl ResponseError {
+    /// Reads the status, url and message of a failed response
+    pub(crate) async fn read(operation: &'static str, resp: reqwest::Response) -> ResponseError {
+        let status = resp.status().as_u16();
+        let url = resp.url().to_string();
+        let body = resp.text().await.unwrap_or_default();
+
+        let message = match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(body) => body.error,
+            Err(_) => body.trim().to_string(),
+        };
+
+        ResponseError {
+            operation,
+            url,
+            status,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} failed with StatusCode {}",
+            self.operation, self.url, self.status
+        )?;
+
+        match self.message.is_empty() {
+            true => Ok(()),
+            false => write!(f, ": {}", self.message),
+        }
+    }
+}
+
+/// Classification shared by the errors of all servers
+///
+/// # Example
+/// ```no_run
+/// # async fn run() {
+/// use rusty_weed::{error::WeedError, utils::FID, volume::Volume};
+///
+/// let volume = Volume::new("localhost", Some(8080));
+/// let fid: FID = "3,01637037d6".parse().unwrap();
+///
+/// match volume.get_file_bytes(&fid, &None).await {
+///     Ok(data) => println!("{} bytes", data.len()),
+///     Err(err) if err.is_not_found() => println!("gone"),
+///     Err(err) if err.is_auth() => println!("missing or expired JWT"),
+///     Err(err) => println!("{}, status {:?}", err, err.status()),
+/// }
+/// # }
+/// ```
+pub trait WeedError: Retryable + std::error::Error {
+    /// The failed response if the server answered at all
+    fn response(&self) -> Option<&ResponseError>;
+
+    /// StatusCode of the failed response
+    fn status(&self) -> Option<u16> {
+        self.response().map(|r| r.status)
+    }
+
+    /// The file, volume or path does not exist
+    fn is_not_found(&self) -> bool {
+        self.class() == ErrorClass::NotFound
+    }
+
+    /// Connection errors, timeouts, interrupted requests and 5xx responses that may succeed on another attempt
+    fn is_retryable(&self) -> bool {
+        RetryPolicy::default().should_retry(self.class())
+    }
+
+    /// The server rejected the JWT or none was sent
+    fn is_auth(&self) -> bool {
+        self.class() == ErrorClass::Unauthorized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{master::MasterErrors, volume::VolumeErrors};
+
+    use super::{ResponseError, WeedError};
+
+    fn response(status: u16, body: &'static str) -> reqwest::Response {
+        let resp = http::Response::builder().status(status).body(body).unwrap();
+        reqwest::Response::from(resp)
+    }
+
+    #[tokio::test]
+    async fn read_error_body() {
+        let body = r#"{"error":"no free volumes"}"#;
+        let err = ResponseError::read("assign", response(406, body)).await;
+        assert_eq!(406, err.status);
+        assert_eq!("no free volumes", err.message);
+
+        let err = ResponseError::read("download", response(500, "internal error\n")).await;
+        assert_eq!("internal error", err.message);
+        assert!(err.to_string().starts_with("download"));
+    }
+
+    #[tokio::test]
+    async fn classify_errors() {
+        let err = VolumeErrors::from_response("download", response(404, "")).await;
+        assert!(err.is_not_found());
+        assert!(!err.is_retryable());
+
+        let err = VolumeErrors::from_response("upload", response(401, "wrong jwt")).await;
+        assert!(err.is_auth());
+        assert_eq!(Some(401), err.status());
+
+        let err = VolumeErrors::from_response("upload", response(400, "")).await;
+        assert!(matches!(err, VolumeErrors::NotCreated(_)));
+
+        let err = MasterErrors::from_response("assign", response(503, "")).await;
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,750 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use bytes::Bytes;
+use reqwest::{header, multipart::Form, Response, Url};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    client::WeedClient,
+    error::{ResponseError, WeedError},
+    utils::{ByteRange, FID},
+};
+
+#[derive(Clone, Debug)]
+pub struct Filer {
+    pub host: String,
+    pub port: Option<u16>,
+    pub(crate) client: WeedClient,
+}
+
+#[derive(Error, Debug)]
+pub enum FilerErrors {
+    #[error("Wrong format of string expected 0.0.0.0:8888 for example")]
+    WrongFormat,
+    #[error("Request was not accepted by the filer: {0}")]
+    InvalidRequest(ResponseError),
+    #[error("File or directory not found on filer: {0}")]
+    NotFound(ResponseError),
+    #[error("Filer rejected the JWT: {0}")]
+    Unauthorized(ResponseError),
+    #[error("Filer failed: {0}")]
+    ServerError(ResponseError),
+    #[error("reqwest error")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("parsing error")]
+    ParseError(#[from] std::num::ParseIntError),
+    #[error("serde query string parsing error")]
+    SerdeQsError(#[from] serde_qs::Error),
+}
+
+impl FilerErrors {
+    /// Maps a failed response to the matching error
+    pub(crate) async fn from_response(operation: &'static str, resp: Response) -> FilerErrors {
+        let err = ResponseError::read(operation, resp).await;
+
+        match err.status {
+            404 => FilerErrors::NotFound(err),
+            401 | 403 => FilerErrors::Unauthorized(err),
+            500..=599 => FilerErrors::ServerError(err),
+            _ => FilerErrors::InvalidRequest(err),
+        }
+    }
+}
+
+impl WeedError for FilerErrors {
+    fn response(&self) -> Option<&ResponseError> {
+        match self {
+            Self::InvalidRequest(e)
+            | Self::NotFound(e)
+            | Self::Unauthorized(e)
+            | Self::ServerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Filer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.port {
+            Some(port) => f.write_str(&concat_string!("http://", self.host, ":", port.to_string())),
+            _ => f.write_str(&concat_string!("http://", self.host, ":8888")),
+        }
+    }
+}
+
+impl FromStr for Filer {
+    type Err = FilerErrors;
+
+    /// Creates a filer from a string
+    ///
+    /// # Example
+    /// ```
+    /// use std::str::FromStr;
+    /// use rusty_weed::filer::Filer;
+    ///
+    /// let filer = Filer::from_str("1.1.1.1:8888").unwrap();
+    /// ```
+    fn from_str(s: &str) -> Result<Filer, FilerErrors> {
+        let mut parts = s.split(':');
+
+        let host: String = match parts.next() {
+            Some(s) => s.to_string(),
+            None => return Err(FilerErrors::WrongFormat),
+        };
+
+        let port: u16 = match parts.next() {
+            Some(s) => s.parse::<u16>()?,
+            None => return Err(FilerErrors::WrongFormat),
+        };
+
+        Ok(Filer::new(&host, Some(port)))
+    }
+}
+
+impl Filer {
+    /// Creates a filer with its own http client
+    ///
+    /// Use [WeedClient::filer](crate::client::WeedClient::filer) to share a connection pool
+    pub fn new(host: &str, port: Option<u16>) -> Filer {
+        Filer::with_client(host, port, WeedClient::new())
+    }
+
+    /// Creates a filer using the given shared client
+    pub fn with_client(host: &str, port: Option<u16>, client: WeedClient) -> Filer {
+        Filer {
+            host: host.to_string(),
+            port,
+            client,
+        }
+    }
+
+    /// Shared http client, see [WeedClient](crate::client::WeedClient)
+    pub fn client(&self) -> &WeedClient {
+        &self.client
+    }
+
+    /// Builds the url for a path, every segment gets percent encoded
+    ///
+    /// A trailing slash is kept since the filer uses it to tell directories apart
+    fn path_url(&self, path: &str, qs_string: &str) -> Result<Url, FilerErrors> {
+        let mut url = match Url::parse(&self.to_string()) {
+            Ok(url) => url,
+            Err(_) => return Err(FilerErrors::WrongFormat),
+        };
+
+        match url.path_segments_mut() {
+            Ok(mut segments) => {
+                segments.clear();
+                segments.extend(path.trim_start_matches('/').split('/'));
+            }
+            Err(_) => return Err(FilerErrors::WrongFormat),
+        }
+
+        if !qs_string.is_empty() {
+            url.set_query(Some(qs_string));
+        }
+
+        Ok(url)
+    }
+
+    /// Uploads a file in bytes to the given path
+    ///
+    /// Retried with the [RetryPolicy](crate::retry::RetryPolicy) of the client,
+    /// appending is never retried since a repeated append would duplicate the data
+    pub async fn upload_file_bytes(
+        &self,
+        path: &str,
+        data: &Bytes,
+        options: &Option<FilerUploadOptions>,
+    ) -> Result<FilerUploadResponse, FilerErrors> {
+        let qs_string = serde_qs::to_string(options)?;
+        let url = self.path_url(path, &qs_string)?;
+
+        let upload = || async {
+            let req = self
+                .client
+                .http()
+                .put(url.clone())
+                .body(data.clone())
+                .send()
+                .await?;
+
+            match req.status() {
+                reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => {
+                    Ok(req.json::<FilerUploadResponse>().await?)
+                }
+                _ => Err(FilerErrors::from_response("upload", req).await),
+            }
+        };
+
+        match options.as_ref().and_then(|o| o.append) {
+            Some(true) => upload().await,
+            _ => self.client.retry().run(upload).await,
+        }
+    }
+
+    /// Uploads a reqwest form to the given path
+    ///
+    /// If the path ends with a slash the file name of the form part is appended.
+    /// The form is consumed by the request so it is never retried.
+    pub async fn upload_file_form(
+        &self,
+        path: &str,
+        data: Form,
+        options: &Option<FilerUploadOptions>,
+    ) -> Result<FilerUploadResponse, FilerErrors> {
+        let qs_string = serde_qs::to_string(options)?;
+
+        let req = self
+            .client
+            .http()
+            .post(self.path_url(path, &qs_string)?)
+            .multipart(data)
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => {
+                Ok(req.json::<FilerUploadResponse>().await?)
+            }
+            _ => Err(FilerErrors::from_response("upload", req).await),
+        }
+    }
+
+    /// Gets a file and returns the full reqwest response
+    ///
+    /// Passing a range requests only part of the file, the status is then 206 Partial Content
+    pub async fn get_file_response(
+        &self,
+        path: &str,
+        range: &Option<ByteRange>,
+    ) -> Result<Response, FilerErrors> {
+        let url = self.path_url(path, "")?;
+
+        self.client
+            .retry()
+            .run(|| async {
+                let mut builder = self.client.http().get(url.clone());
+
+                if let Some(range) = range {
+                    builder = builder.header(header::RANGE, range.to_string());
+                }
+
+                let req = builder.send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK | reqwest::StatusCode::PARTIAL_CONTENT => Ok(req),
+                    _ => Err(FilerErrors::from_response("download", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Gets a file and returns it in bytes
+    pub async fn get_file_bytes(
+        &self,
+        path: &str,
+        range: &Option<ByteRange>,
+    ) -> Result<Bytes, FilerErrors> {
+        let req = self.get_file_response(path, range).await?;
+
+        Ok(req.bytes().await?)
+    }
+
+    /// Lists a directory
+    ///
+    /// Use [last_file_name](ListDirectoryResponse::last_file_name) of the response
+    /// as [last_file_name](ListDirectoryOptions::last_file_name) to get the next page
+    pub async fn list_directory(
+        &self,
+        path: &str,
+        options: &Option<ListDirectoryOptions>,
+    ) -> Result<ListDirectoryResponse, FilerErrors> {
+        let qs_string = serde_qs::to_string(options)?;
+
+        let mut dir = path.to_string();
+        if !dir.ends_with('/') {
+            dir.push('/');
+        }
+
+        let url = self.path_url(&dir, &qs_string)?;
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self
+                    .client
+                    .http()
+                    .get(url.clone())
+                    .header(header::ACCEPT, "application/json")
+                    .send()
+                    .await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req.json::<ListDirectoryResponse>().await?),
+                    _ => Err(FilerErrors::from_response("list", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Creates a directory including all missing parents
+    pub async fn create_directory(&self, path: &str) -> Result<(), FilerErrors> {
+        let mut dir = path.to_string();
+        if !dir.ends_with('/') {
+            dir.push('/');
+        }
+
+        let url = self.path_url(&dir, "")?;
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self.client.http().post(url.clone()).send().await?;
+
+                match req.status().is_success() {
+                    true => Ok(()),
+                    false => Err(FilerErrors::from_response("create directory", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Deletes a file or directory
+    ///
+    /// A 404 on a retry counts as deleted, the earlier attempt may have gone through
+    pub async fn delete(
+        &self,
+        path: &str,
+        options: &Option<FilerDeleteOptions>,
+    ) -> Result<(), FilerErrors> {
+        let qs_string = serde_qs::to_string(options)?;
+        let url = self.path_url(path, &qs_string)?;
+        let attempts = AtomicU32::new(0);
+
+        self.client
+            .retry()
+            .run(|| async {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+                let req = self.client.http().delete(url.clone()).send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK
+                    | reqwest::StatusCode::ACCEPTED
+                    | reqwest::StatusCode::NO_CONTENT => Ok(()),
+                    reqwest::StatusCode::NOT_FOUND if attempt > 1 => Ok(()),
+                    _ => Err(FilerErrors::from_response("delete", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Moves or renames a file or directory
+    ///
+    /// Not retried, a repeated move fails once the source is gone
+    pub async fn move_path(&self, from: &str, to: &str) -> Result<(), FilerErrors> {
+        let qs_string = serde_qs::to_string(&MoveOptions {
+            from: from.to_string(),
+        })?;
+
+        let req = self
+            .client
+            .http()
+            .post(self.path_url(to, &qs_string)?)
+            .send()
+            .await?;
+
+        match req.status() {
+            status if status.is_success() => Ok(()),
+            _ => Err(FilerErrors::from_response("move", req).await),
+        }
+    }
+
+    /// Gets the metadata of a file or directory
+    pub async fn get_metadata(&self, path: &str) -> Result<Entry, FilerErrors> {
+        let url = self.path_url(path, "metadata=true")?;
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self
+                    .client
+                    .http()
+                    .get(url.clone())
+                    .header(header::ACCEPT, "application/json")
+                    .send()
+                    .await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req.json::<Entry>().await?),
+                    _ => Err(FilerErrors::from_response("metadata", req).await),
+                }
+            })
+            .await
+    }
+}
+
+/// Options for the filer functions [upload_file_bytes](Filer::upload_file_bytes) and [upload_file_form](Filer::upload_file_form)
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FilerUploadOptions {
+    pub collection: Option<String>,
+    pub replication: Option<crate::utils::ReplicationType>,
+    pub ttl: Option<crate::utils::TTL>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    /// Max size in MB of one chunk, larger files get split automatically
+    #[serde(rename = "maxMB")]
+    pub max_mb: Option<u32>,
+    /// Appends the data to an existing file
+    #[serde(rename = "op")]
+    #[serde(serialize_with = "serialize_append")]
+    pub append: Option<bool>,
+}
+
+fn serialize_append<S>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match value {
+        Some(true) => serializer.serialize_str("append"),
+        _ => serializer.serialize_none(),
+    }
+}
+
+/// Return type for the filer upload functions
+#[derive(Deserialize, Debug, Default)]
+pub struct FilerUploadResponse {
+    pub name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub fid: Option<FID>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Options for the filer function [list_directory](Filer::list_directory)
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDirectoryOptions {
+    /// Max number of entries, the filer defaults to 100
+    pub limit: Option<u32>,
+    /// Starts the listing after this file name
+    pub last_file_name: Option<String>,
+    /// Only lists entries matching the pattern, for example *.jpg
+    pub name_pattern: Option<String>,
+}
+
+/// Return type of the filer function [list_directory](Filer::list_directory)
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListDirectoryResponse {
+    pub path: String,
+    #[serde(default)]
+    pub entries: Option<Vec<Entry>>,
+    pub limit: u32,
+    pub last_file_name: String,
+    pub should_display_load_more: bool,
+    #[serde(default)]
+    pub empty_folder: bool,
+}
+
+/// A file or directory stored in the filer
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct Entry {
+    pub full_path: String,
+    /// Modification time in RFC 3339
+    pub mtime: String,
+    /// Creation time in RFC 3339
+    pub crtime: String,
+    /// Unix file mode, directories have the bit 1 << 31 set
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    #[serde(default)]
+    pub mime: String,
+    #[serde(default)]
+    pub ttl_sec: i32,
+    #[serde(default)]
+    pub user_name: String,
+    #[serde(default)]
+    pub group_names: Option<Vec<String>>,
+    #[serde(default)]
+    pub symlink_target: String,
+    /// Base64 encoded md5 of the content
+    #[serde(default)]
+    pub md5: Option<String>,
+    #[serde(default)]
+    pub file_size: u64,
+    #[serde(default)]
+    pub extended: Option<HashMap<String, String>>,
+    #[serde(default, rename = "chunks")]
+    pub chunks: Option<Vec<FileChunk>>,
+}
+
+impl Entry {
+    /// Whether the entry is a directory
+    pub fn is_directory(&self) -> bool {
+        self.mode & (1 << 31) != 0
+    }
+}
+
+/// A chunk of a file stored on a volume server
+#[derive(Deserialize, Debug, Default)]
+pub struct FileChunk {
+    pub file_id: String,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_ts_ns: i64,
+    #[serde(default)]
+    pub e_tag: String,
+}
+
+/// Options for the filer function [delete](Filer::delete)
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FilerDeleteOptions {
+    /// Deletes directories with all children
+    pub recursive: Option<bool>,
+    /// Ignores errors while deleting children
+    pub ignore_recursive_error: Option<bool>,
+    /// Only deletes the metadata and keeps the chunks on the volume servers
+    pub skip_chunk_deletion: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+struct MoveOptions {
+    #[serde(rename = "mv.from")]
+    from: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        client::WeedClient,
+        error::WeedError,
+        retry::RetryPolicy,
+        testing::{Faults, MockServer},
+    };
+
+    use super::{
+        Filer, FilerDeleteOptions, FilerUploadOptions, ListDirectoryOptions, ListDirectoryResponse,
+    };
+
+    #[test]
+    fn path_url_encoding() {
+        let filer = Filer::new("localhost", None);
+
+        let url = filer.path_url("/dir/some file.txt", "").unwrap();
+        assert_eq!("http://localhost:8888/dir/some%20file.txt", url.as_str());
+
+        let url = filer.path_url("dir/sub/", "mv.from=%2Fa").unwrap();
+        assert_eq!("http://localhost:8888/dir/sub/?mv.from=%2Fa", url.as_str());
+    }
+
+    #[test]
+    fn serialize_delete_options() {
+        let options = FilerDeleteOptions {
+            recursive: Some(true),
+            ignore_recursive_error: Some(true),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "recursive=true&ignoreRecursiveError=true",
+            serde_qs::to_string(&options).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_upload_options() {
+        let options = FilerUploadOptions {
+            collection: Some("docs".to_string()),
+            max_mb: Some(4),
+            append: Some(true),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "collection=docs&maxMB=4&op=append",
+            serde_qs::to_string(&options).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_list_directory() {
+        let data = r#"{
+            "Path": "/dir",
+            "Entries": [
+                {
+                    "FullPath": "/dir/sub",
+                    "Mtime": "2023-03-20T10:00:00Z",
+                    "Crtime": "2023-03-20T10:00:00Z",
+                    "Mode": 2147484141,
+                    "Uid": 0,
+                    "Gid": 0,
+                    "Mime": "",
+                    "TtlSec": 0,
+                    "UserName": "",
+                    "GroupNames": null,
+                    "SymlinkTarget": "",
+                    "Md5": null,
+                    "FileSize": 0,
+                    "Extended": null,
+                    "HardLinkId": null,
+                    "HardLinkCounter": 0
+                },
+                {
+                    "FullPath": "/dir/hello.txt",
+                    "Mtime": "2023-03-20T10:00:00Z",
+                    "Crtime": "2023-03-20T10:00:00Z",
+                    "Mode": 432,
+                    "Uid": 0,
+                    "Gid": 0,
+                    "Mime": "text/plain",
+                    "TtlSec": 0,
+                    "Md5": "7Qdih1MuhjZehB6Sv8UNjA==",
+                    "FileSize": 12,
+                    "chunks": [
+                        {
+                            "file_id": "3,01637037d6",
+                            "size": 12,
+                            "modified_ts_ns": 1679306400000000000,
+                            "e_tag": "ed076287532e86365e841e92bfc50d8c"
+                        }
+                    ]
+                }
+            ],
+            "Limit": 100,
+            "LastFileName": "hello.txt",
+            "ShouldDisplayLoadMore": false,
+            "EmptyFolder": false
+        }"#;
+
+        let parsed = serde_json::from_str::<ListDirectoryResponse>(data).unwrap();
+        let entries = parsed.entries.unwrap();
+
+        assert_eq!("hello.txt", parsed.last_file_name);
+        assert!(entries[0].is_directory());
+        assert!(!entries[1].is_directory());
+        assert_eq!(12, entries[1].file_size);
+        assert_eq!("3,01637037d6", entries[1].chunks.as_ref().unwrap()[0].file_id);
+    }
+
+    #[tokio::test]
+    async fn directory_round_trip() {
+        let server = MockServer::start().await;
+        let filer = server.filer();
+
+        filer.create_directory("/docs/empty").await.unwrap();
+        filer
+            .upload_file_bytes("/docs/a.txt", &Bytes::from("Hello"), &None)
+            .await
+            .unwrap();
+        filer
+            .upload_file_bytes("/docs/b.txt", &Bytes::from("World!"), &None)
+            .await
+            .unwrap();
+
+        let listing = filer.list_directory("/docs", &None).await.unwrap();
+        let paths: Vec<String> = listing
+            .entries
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.full_path)
+            .collect();
+        assert_eq!(vec!["/docs/a.txt", "/docs/b.txt", "/docs/empty"], paths);
+
+        let options = ListDirectoryOptions {
+            limit: Some(1),
+            last_file_name: Some("a.txt".to_string()),
+            ..Default::default()
+        };
+        let page = filer.list_directory("/docs", &Some(options)).await.unwrap();
+        assert_eq!("b.txt", page.last_file_name);
+        assert!(page.should_display_load_more);
+
+        filer.move_path("/docs", "/archive").await.unwrap();
+        assert!(filer
+            .get_metadata("/archive/empty")
+            .await
+            .unwrap()
+            .is_directory());
+        assert_eq!(
+            6,
+            filer
+                .get_metadata("/archive/b.txt")
+                .await
+                .unwrap()
+                .file_size
+        );
+        assert!(filer
+            .get_metadata("/docs/a.txt")
+            .await
+            .unwrap_err()
+            .is_not_found());
+
+        assert!(filer.delete("/archive", &None).await.is_err());
+        let options = FilerDeleteOptions {
+            recursive: Some(true),
+            ..Default::default()
+        };
+        filer.delete("/archive", &Some(options)).await.unwrap();
+
+        let root = filer.list_directory("/", &None).await.unwrap();
+        assert!(root.empty_folder);
+        assert!(filer
+            .get_file_bytes("/archive/a.txt", &None)
+            .await
+            .unwrap_err()
+            .is_not_found());
+    }
+
+    #[tokio::test]
+    async fn retries_through_policy() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy {
+                max_attempts: 2,
+                base_delay: std::time::Duration::ZERO,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client).await;
+        let filer = server.filer();
+
+        server.inject(Faults {
+            server_errors: 1,
+            ..Default::default()
+        });
+        filer.create_directory("/retried").await.unwrap();
+        assert_eq!(2, server.requests());
+
+        server.inject(Faults {
+            server_errors: 2,
+            ..Default::default()
+        });
+        assert_eq!(
+            Some(500),
+            filer
+                .list_directory("/retried", &None)
+                .await
+                .unwrap_err()
+                .status()
+        );
+    }
+}
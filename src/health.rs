@@ -0,0 +1,274 @@
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use serde::{Serialize, Serializer};
+
+use crate::{
+    cluster::MasterCluster,
+    master::MasterErrors,
+    topology::{DataNode, Topology},
+};
+
+/// Options for the health [check](check)
+#[derive(Debug, Clone)]
+pub struct HealthCheckOptions {
+    /// Time a volume server gets to answer before it counts as unreachable
+    pub timeout: Duration,
+}
+
+impl Default for HealthCheckOptions {
+    /// Volume servers have 5 seconds to answer
+    fn default() -> Self {
+        HealthCheckOptions {
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Result of a health [check](check), serializable to expose it from a readiness endpoint
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct HealthReport {
+    /// The masters have a leader and accept requests
+    pub cluster_healthy: bool,
+    pub volume_servers: Vec<VolumeServerHealth>,
+}
+
+impl HealthReport {
+    /// Healthy cluster with every volume server reachable
+    pub fn is_ready(&self) -> bool {
+        self.cluster_healthy && self.volume_servers.iter().all(|v| v.reachable)
+    }
+
+    /// Volume servers that did not answer
+    pub fn unreachable(&self) -> impl Iterator<Item = &VolumeServerHealth> {
+        self.volume_servers.iter().filter(|v| !v.reachable)
+    }
+}
+
+/// Health of a single volume server
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct VolumeServerHealth {
+    pub url: String,
+    pub public_url: String,
+    pub data_center: String,
+    pub rack: String,
+    pub reachable: bool,
+    /// Round trip of the ping, serialized in milliseconds
+    #[serde(serialize_with = "serialize_millis")]
+    pub latency: Option<Duration>,
+    /// Why the ping failed
+    pub error: Option<String>,
+    /// Free volume slots reported by the master
+    pub free_slots: i64,
+    pub max_slots: i64,
+    /// Ids of the read only volumes reported by the master
+    pub read_only_volumes: Vec<u32>,
+}
+
+fn serialize_millis<S>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(d) => serializer.serialize_u64(d.as_millis() as u64),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Checks /cluster/healthz, lists the volume servers of the topology and pings each of them
+///
+/// Fails only if no master can be asked, unreachable volume servers are part of the report.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> Result<(), rusty_weed::master::MasterErrors> {
+/// use rusty_weed::{cluster::MasterCluster, health, master::Master};
+///
+/// let masters = MasterCluster::from(Master::new("localhost", Some(9333)));
+/// let report = health::check(&masters, &None).await?;
+///
+/// for server in report.unreachable() {
+///     println!("{} is down: {:?}", server.url, server.error);
+/// }
+/// println!("ready: {}", report.is_ready());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn check(
+    masters: &MasterCluster,
+    options: &Option<HealthCheckOptions>,
+) -> Result<HealthReport, MasterErrors> {
+    let options = options.clone().unwrap_or_default();
+
+    let cluster_healthy = masters
+        .call(|master| async move { master.cluster_healthz().await })
+        .await?;
+
+    if !cluster_healthy {
+        return Ok(HealthReport::default());
+    }
+
+    let topology = masters
+        .call(|master| async move { master.topology().await })
+        .await?;
+
+    let pings = servers(&topology)
+        .into_iter()
+        .map(|server| ping(masters, server, options.timeout));
+
+    Ok(HealthReport {
+        cluster_healthy,
+        volume_servers: join_all(pings).await,
+    })
+}
+
+/// Volume servers of the topology as reported by the master, not yet pinged
+fn servers(topology: &Topology) -> Vec<VolumeServerHealth> {
+    let mut servers = Vec::new();
+
+    for dc in &topology.data_centers {
+        for rack in &dc.racks {
+            for node in &rack.data_nodes {
+                servers.push(VolumeServerHealth {
+                    url: node.url.clone(),
+                    public_url: node.public_url.clone(),
+                    data_center: dc.id.clone(),
+                    rack: rack.id.clone(),
+                    free_slots: node.free,
+                    max_slots: node.max,
+                    read_only_volumes: read_only_volumes(node),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    servers
+}
+
+fn read_only_volumes(node: &DataNode) -> Vec<u32> {
+    node.volumes
+        .iter()
+        .filter(|v| v.read_only)
+        .map(|v| v.id)
+        .collect()
+}
+
+async fn ping(
+    masters: &MasterCluster,
+    mut server: VolumeServerHealth,
+    timeout: Duration,
+) -> VolumeServerHealth {
+    let volume = match masters.client().volume_from_str(&server.url) {
+        Ok(volume) => volume,
+        Err(err) => {
+            server.error = Some(err.to_string());
+            return server;
+        }
+    };
+
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, volume.status()).await {
+        Ok(Ok(_)) => {
+            server.reachable = true;
+            server.latency = Some(start.elapsed());
+        }
+        Ok(Err(err)) => server.error = Some(err.to_string()),
+        Err(_) => server.error = Some("timed out".to_string()),
+    }
+
+    server
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cluster::MasterCluster,
+        testing::{Faults, MockServer},
+        topology::{DataCenter, DataNode, Rack, Topology, VolumeInfo},
+    };
+
+    use super::{check, servers, HealthReport};
+
+    #[test]
+    fn servers_from_topology() {
+        let node = DataNode {
+            url: "1.1.1.1:8080".to_string(),
+            max: 8,
+            free: 2,
+            volumes: vec![
+                VolumeInfo {
+                    id: 1,
+                    read_only: true,
+                    ..Default::default()
+                },
+                VolumeInfo {
+                    id: 2,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let topology = Topology {
+            data_centers: vec![DataCenter {
+                id: "dc1".to_string(),
+                racks: vec![Rack {
+                    id: "rack1".to_string(),
+                    data_nodes: vec![node],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let servers = servers(&topology);
+        assert_eq!(1, servers.len());
+        assert_eq!("dc1", servers[0].data_center);
+        assert_eq!(2, servers[0].free_slots);
+        assert_eq!(vec![1], servers[0].read_only_volumes);
+
+        let report = HealthReport {
+            cluster_healthy: true,
+            volume_servers: servers,
+        };
+        assert!(!report.is_ready());
+        assert_eq!(1, report.unreachable().count());
+    }
+
+    #[tokio::test]
+    async fn check_mock_cluster() {
+        let server = MockServer::start().await;
+        let masters = MasterCluster::from(server.master());
+
+        let report = check(&masters, &None).await.unwrap();
+        assert!(report.is_ready());
+        assert_eq!(1, report.volume_servers.len());
+
+        let health = &report.volume_servers[0];
+        assert_eq!(format!("{}:{}", server.host(), server.port()), health.url);
+        assert_eq!("DefaultDataCenter", health.data_center);
+        assert_eq!("DefaultRack", health.rack);
+        assert_eq!((7, 8), (health.free_slots, health.max_slots));
+        assert!(health.reachable);
+        assert!(health.latency.is_some());
+        assert!(health.error.is_none());
+
+        // only the ping of the volume server fails, the master keeps answering
+        server.inject(Faults {
+            dropped_connections: 1,
+            path_prefix: Some("/status".to_string()),
+            ..Default::default()
+        });
+
+        let report = check(&masters, &None).await.unwrap();
+        assert!(report.cluster_healthy);
+        assert!(!report.is_ready());
+
+        let unreachable: Vec<_> = report.unreachable().collect();
+        assert_eq!(1, unreachable.len());
+        assert_eq!("DefaultRack", unreachable[0].rack);
+        assert!(unreachable[0].latency.is_none());
+        assert!(unreachable[0].error.is_some());
+    }
+}
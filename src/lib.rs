@@ -1,11 +1,55 @@
 #[macro_use(concat_string)]
 extern crate concat_string;
 
+/// Contains the shared [client](crate::client::WeedClient) that hands out masters, volumes and filers
+pub mod client;
+
 /// Contains the [master](crate::master::Master) struct that implements all master server endpoints
 pub mod master;
 
+/// Contains the [master cluster](crate::cluster::MasterCluster) with leader discovery and failover
+pub mod cluster;
+
+/// Contains the typed [topology](crate::topology::Topology) of data centers, racks, data nodes and volumes
+pub mod topology;
+
+/// Contains the cluster wide health [check](crate::health::check) for readiness probes
+pub mod health;
+
+/// Contains the [response error](crate::error::ResponseError) and the [classification](crate::error::WeedError) shared by all errors
+pub mod error;
+
+/// Contains the [retry policy](crate::retry::RetryPolicy) applied to master, volume and filer requests
+pub mod retry;
+
+/// Contains synchronous wrappers of the [master](crate::blocking::Master) and [volume](crate::blocking::Volume)
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 /// Contains the [volume](crate::volume::Volume) struct that implements all volume server endpoints
 pub mod volume;
 
+/// Contains the [filer](crate::filer::Filer) struct that implements the path based filer server endpoints
+pub mod filer;
+
+/// Contains the [JWT signer](crate::security::JwtSigner) for clusters with jwt.signing keys
+pub mod security;
+
+/// Contains the [location cache](crate::cache::LocationCache) used by the store to skip master lookups
+pub mod cache;
+
+/// Contains the [store](crate::store::Store) that combines master and volumes behind put, get and delete
+pub mod store;
+
+/// Contains the [chunk manifest](crate::chunk::ChunkManifest) used to upload large files in chunks
+pub mod chunk;
+
+/// Contains the [file stream](crate::stream::FileStream) returned by streaming downloads
+pub mod stream;
+
+/// Contains the in-memory [mock server](crate::testing::MockServer) for offline tests
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 /// Holds universal structs like the [FID](crate::utils::FID) and [Locations](crate::utils::Location)
 pub mod utils;
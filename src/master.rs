@@ -1,20 +1,41 @@
+use std::{fmt, str::FromStr};
+
 use thiserror::Error;
 
 use serde::{Deserialize, Serialize};
 
-use crate::utils::{self, Location, FID};
-
+use crate::{
+    client::WeedClient,
+    error::{ResponseError, WeedError},
+    topology::{Topology, VolumeStatus},
+    utils::{self, Location, FID},
+};
+
+/// Address of a master server and the http client used to talk to it
+///
+/// Created with [new](Master::new), [with_client](Master::with_client), [from_str](Master::from_str)
+/// or by a [WeedClient](crate::client::WeedClient), the client field keeps struct literals out.
+#[derive(Clone, Debug)]
 pub struct Master {
     pub host: String,
     pub port: Option<u16>,
+    pub(crate) client: WeedClient,
 }
 
 #[derive(Error, Debug)]
 pub enum MasterErrors {
     #[error("Wrong format of string expected 0.0.0.0:3333 for example")]
     WrongFormat,
-    #[error("Response StatusCode was not OK see body for error: {0}")]
-    InvalidRequest(String),
+    #[error("Request was not accepted by the master: {0}")]
+    InvalidRequest(ResponseError),
+    #[error("Volume not found on master: {0}")]
+    VolumeNotFound(ResponseError),
+    #[error("Master rejected the JWT: {0}")]
+    Unauthorized(ResponseError),
+    #[error("Master failed: {0}")]
+    ServerError(ResponseError),
+    #[error("None of the masters in the cluster could be reached")]
+    NoMasterAvailable,
     #[error("reqwest error")]
     ReqwestError(#[from] reqwest::Error),
     #[error("parsing error")]
@@ -23,84 +44,349 @@ pub enum MasterErrors {
     SerdeQsError(#[from] serde_qs::Error),
 }
 
-impl Master {
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Master {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.port {
-            Some(port) => concat_string!("http://", self.host, ":", port.to_string()),
-            _ => concat_string!("http://", self.host, ":9333"),
+            Some(port) => f.write_str(&concat_string!("http://", self.host, ":", port.to_string())),
+            _ => f.write_str(&concat_string!("http://", self.host, ":9333")),
         }
     }
+}
 
-    /// Creates a master from a string
-    /// 
+impl FromStr for Master {
+    type Err = MasterErrors;
+
+    /// Parses a master like 1.1.1.1:9333
+    ///
     /// # Example
     /// ```
     /// use rusty_weed::master::Master;
-    /// 
-    /// let master = Master::from_str("1.1.1.1:9333").unwrap();
+    ///
+    /// let master: Master = "1.1.1.1:9333".parse().unwrap();
     /// ```
-    pub fn from_str(s: &str) -> Result<Master, MasterErrors> {
-        let mut parts = s.split(":");
+    fn from_str(s: &str) -> Result<Master, MasterErrors> {
+        let mut parts = s.split(':');
 
-        let host: String;
-        let port: u16;
+        let host: String = match parts.next() {
+            Some(s) => s.to_string(),
+            None => return Err(MasterErrors::WrongFormat),
+        };
 
-        match parts.next() {
-            Some(s) => host = s.to_string(),
+        let port: u16 = match parts.next() {
+            Some(s) => s.parse::<u16>()?,
             None => return Err(MasterErrors::WrongFormat),
+        };
+
+        if parts.next().is_some() {
+            return Err(MasterErrors::WrongFormat);
         }
 
-        match parts.next() {
-            Some(s) => port = s.parse::<u16>()?,
-            None => return Err(MasterErrors::WrongFormat),
+        Ok(Master::new(&host, Some(port)))
+    }
+}
+
+impl MasterErrors {
+    /// Maps a failed response to the matching error
+    pub(crate) async fn from_response(
+        operation: &'static str,
+        resp: reqwest::Response,
+    ) -> MasterErrors {
+        let err = ResponseError::read(operation, resp).await;
+
+        match err.status {
+            404 => MasterErrors::VolumeNotFound(err),
+            401 | 403 => MasterErrors::Unauthorized(err),
+            500..=599 => MasterErrors::ServerError(err),
+            _ => MasterErrors::InvalidRequest(err),
         }
+    }
+}
 
-        Ok(Master {
-            host,
-            port: Some(port),
-        })
+impl WeedError for MasterErrors {
+    fn response(&self) -> Option<&ResponseError> {
+        match self {
+            Self::InvalidRequest(e)
+            | Self::VolumeNotFound(e)
+            | Self::Unauthorized(e)
+            | Self::ServerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Master {
+    /// Creates a master with its own http client
+    ///
+    /// Use [WeedClient::master](crate::client::WeedClient::master) to share a connection pool
+    pub fn new(host: &str, port: Option<u16>) -> Master {
+        Master::with_client(host, port, WeedClient::new())
+    }
+
+    /// Creates a master using the given shared client
+    pub fn with_client(host: &str, port: Option<u16>, client: WeedClient) -> Master {
+        Master {
+            host: host.to_string(),
+            port,
+            client,
+        }
+    }
+
+    /// Shared http client, see [WeedClient](crate::client::WeedClient)
+    pub fn client(&self) -> &WeedClient {
+        &self.client
+    }
+
+    /// Creates a master from a string
+    ///
+    /// # Example
+    /// ```
+    /// use rusty_weed::master::Master;
+    ///
+    /// let master = Master::from_str("1.1.1.1:9333").unwrap();
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Master, MasterErrors> {
+        <Master as FromStr>::from_str(s)
     }
 
     /// Assigns a file id
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
     pub async fn assign_key(
         &self,
         options: &Option<AssignKeyOptions>,
     ) -> Result<AssignKeyResponse, MasterErrors> {
-        let qs_string = serde_qs::to_string(options)?;
-        let req = reqwest::get(concat_string!(self.to_string(), "/dir/assign?", qs_string)).await?;
-
-        match req.status() {
-            reqwest::StatusCode::OK => Ok(req.json::<AssignKeyResponse>().await?),
-            _ => Err(MasterErrors::InvalidRequest(req.text().await?)),
-        }
+        let url = concat_string!(
+            self.to_string(),
+            "/dir/assign?",
+            serde_qs::to_string(options)?
+        );
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self.client.http().get(&url).send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req.json::<AssignKeyResponse>().await?),
+                    _ => Err(MasterErrors::from_response("assign", req).await),
+                }
+            })
+            .await
     }
 
-    /// Lookup the locations of a volume
+    /// Lookup the locations of the volume of a file id
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
     pub async fn lookup_volume(
         &self,
         volume_id: &FID,
         options: &Option<LookupVolumeOptions>,
     ) -> Result<LookupVolumeResponse, MasterErrors> {
-        let qs_string = serde_qs::to_string(options)?;
+        self.lookup_volume_id(volume_id.volume_id, options).await
+    }
 
-        let req = reqwest::get(concat_string!(
+    /// Lookup the locations of a volume by its id
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
+    pub async fn lookup_volume_id(
+        &self,
+        volume_id: u32,
+        options: &Option<LookupVolumeOptions>,
+    ) -> Result<LookupVolumeResponse, MasterErrors> {
+        let url = concat_string!(
             self.to_string(),
             "/dir/lookup?volumeId=",
-            volume_id.volume_id.to_string(),
+            volume_id.to_string(),
             "&",
-            qs_string
-        ))
-        .await?;
+            serde_qs::to_string(options)?
+        );
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self.client.http().get(&url).send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req.json::<LookupVolumeResponse>().await?),
+                    _ => Err(MasterErrors::from_response("lookup", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Raft status of the master cluster including the current leader
+    pub async fn cluster_status(&self) -> Result<ClusterStatusResponse, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/cluster/status"))
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK => Ok(req.json::<ClusterStatusResponse>().await?),
+            _ => Err(MasterErrors::from_response("cluster status", req).await),
+        }
+    }
+
+    /// Whether the cluster has a leader and accepts requests, false if the master answered 503
+    pub async fn cluster_healthz(&self) -> Result<bool, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/cluster/healthz"))
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK => Ok(true),
+            reqwest::StatusCode::SERVICE_UNAVAILABLE => Ok(false),
+            _ => Err(MasterErrors::from_response("cluster health", req).await),
+        }
+    }
+
+    /// Topology of the cluster with the number of volumes per data node
+    pub async fn dir_status(&self) -> Result<DirStatusResponse, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/dir/status"))
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK => Ok(req.json::<DirStatusResponse>().await?),
+            _ => Err(MasterErrors::from_response("dir status", req).await),
+        }
+    }
+
+    /// All volumes of the cluster keyed by data center, rack and data node
+    pub async fn volume_status(&self) -> Result<VolumeStatusResponse, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/vol/status"))
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK => Ok(req.json::<VolumeStatusResponse>().await?),
+            _ => Err(MasterErrors::from_response("volume status", req).await),
+        }
+    }
+
+    /// Topology of the cluster with the volumes of every data node
+    ///
+    /// Combines [dir_status](Master::dir_status) and [volume_status](Master::volume_status)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), rusty_weed::master::MasterErrors> {
+    /// use rusty_weed::master::Master;
+    ///
+    /// let master = Master::new("localhost", Some(9333));
+    /// let topology = master.topology().await?;
+    ///
+    /// for node in topology.data_nodes() {
+    ///     let read_only = node.volumes.iter().filter(|v| v.read_only).count();
+    ///     println!("{} free slots {}, read only volumes {}", node.url, node.free, read_only);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn topology(&self) -> Result<Topology, MasterErrors> {
+        let mut topology = self.dir_status().await?.topology;
+        let status = self.volume_status().await?;
+        topology.fill_volumes(&status.volumes);
+
+        Ok(topology)
+    }
+
+    /// Creates new volumes, for example to prepare a collection for a new tenant
+    ///
+    /// Not retried since every call grows the cluster again
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), rusty_weed::master::MasterErrors> {
+    /// use rusty_weed::master::{GrowVolumeOptions, Master};
+    ///
+    /// let master = Master::new("localhost", Some(9333));
+    /// let options = GrowVolumeOptions {
+    ///     count: Some(4),
+    ///     collection: Some("tenant".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let resp = master.grow_volume(&Some(options)).await?;
+    /// println!("{} volumes created", resp.count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn grow_volume(
+        &self,
+        options: &Option<GrowVolumeOptions>,
+    ) -> Result<GrowVolumeResponse, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(
+                self.to_string(),
+                "/vol/grow?",
+                serde_qs::to_string(options)?
+            ))
+            .send()
+            .await?;
 
         match req.status() {
-            reqwest::StatusCode::OK => Ok(req.json::<LookupVolumeResponse>().await?),
-            _ => Err(MasterErrors::InvalidRequest(req.text().await?)),
+            reqwest::StatusCode::OK => Ok(req.json::<GrowVolumeResponse>().await?),
+            _ => Err(MasterErrors::from_response("grow", req).await),
+        }
+    }
+
+    /// Compacts volumes whose share of deleted content is above the garbage threshold
+    ///
+    /// Answered after the vacuum finished with the status of the topology
+    pub async fn vacuum_volumes(
+        &self,
+        options: &Option<VacuumVolumeOptions>,
+    ) -> Result<DirStatusResponse, MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(
+                self.to_string(),
+                "/vol/vacuum?",
+                serde_qs::to_string(options)?
+            ))
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK => Ok(req.json::<DirStatusResponse>().await?),
+            _ => Err(MasterErrors::from_response("vacuum", req).await),
+        }
+    }
+
+    /// Deletes a collection with all its volumes and files
+    pub async fn delete_collection(&self, collection: &str) -> Result<(), MasterErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/col/delete"))
+            .query(&[("collection", collection)])
+            .send()
+            .await?;
+
+        match req.status() {
+            reqwest::StatusCode::OK | reqwest::StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(MasterErrors::from_response("delete collection", req).await),
         }
     }
 }
 
 /// Options for the [assign_key](Master::assign_key) function
-#[derive(Serialize, Debug, Default)]
+#[derive(Serialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AssignKeyOptions {
     pub count: Option<u32>,
@@ -127,6 +413,56 @@ pub struct AssignKeyResponse {
     pub fid: FID,
     #[serde(flatten)]
     pub location: Location,
+    /// JWT for writing the fid, only set if the cluster has a signing key configured
+    #[serde(default)]
+    pub auth: Option<String>,
+}
+
+impl AssignKeyResponse {
+    /// Every file id reserved by the assignment with the volume server to upload to
+    ///
+    /// An assignment with a count of n reserves `fid`, `fid_1` up to `fid_{n-1}`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), rusty_weed::master::MasterErrors> {
+    /// use rusty_weed::master::{AssignKeyOptions, Master};
+    ///
+    /// let master = Master::new("localhost", Some(9333));
+    /// let options = AssignKeyOptions {
+    ///     count: Some(3),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// for assigned in master.assign_key(&Some(options)).await?.fids() {
+    ///     println!("upload {} to {}", assigned.fid.to_string(), assigned.location.url);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fids(&self) -> impl Iterator<Item = AssignedFid> + '_ {
+        (0..self.count.max(1)).map(move |n| AssignedFid {
+            fid: FID {
+                count: match n {
+                    0 => self.fid.count,
+                    _ => Some(n),
+                },
+                ..self.fid.clone()
+            },
+            location: self.location.clone(),
+            auth: self.auth.clone(),
+        })
+    }
+}
+
+/// A single file id of an assignment, see [fids](AssignKeyResponse::fids)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignedFid {
+    pub fid: FID,
+    /// Volume server to upload to
+    pub location: Location,
+    /// JWT of the assignment, valid for each of its file ids
+    pub auth: Option<String>,
 }
 
 /// Options for the [lookup_volume](Master::lookup_volume) function
@@ -144,14 +480,139 @@ pub struct LookupVolumeResponse {
     pub locations: Vec<Location>,
 }
 
+/// Options for the [grow_volume](Master::grow_volume) function
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GrowVolumeOptions {
+    /// Number of volumes to create, default depends on the replication
+    pub count: Option<u32>,
+    pub collection: Option<String>,
+    pub replication: Option<utils::ReplicationType>,
+    pub ttl: Option<utils::TTL>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    pub data_node: Option<String>,
+    /// Pre-allocate this number of bytes on disk for the new volumes
+    pub preallocate: Option<u64>,
+    /// Disk type to create the volumes on
+    pub disk: Option<String>,
+}
+
+/// Return type of the [grow_volume](Master::grow_volume) function
+#[derive(Deserialize, Debug, Default)]
+pub struct GrowVolumeResponse {
+    /// Number of volumes created
+    pub count: u32,
+}
+
+/// Options for the [vacuum_volumes](Master::vacuum_volumes) function
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VacuumVolumeOptions {
+    /// Share of deleted content above which a volume is compacted. Default: 0.3
+    pub garbage_threshold: Option<f32>,
+    /// Only vacuum the volumes of this collection
+    pub collection: Option<String>,
+    /// Only vacuum this volume
+    pub volume_id: Option<u32>,
+}
+
+/// Return type of the [dir_status](Master::dir_status) and [vacuum_volumes](Master::vacuum_volumes) functions
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct DirStatusResponse {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub topology: Topology,
+}
+
+/// Return type of the [volume_status](Master::volume_status) function
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct VolumeStatusResponse {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub volumes: VolumeStatus,
+}
+
+/// Return type of the [cluster_status](Master::cluster_status) function
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ClusterStatusResponse {
+    pub is_leader: bool,
+    /// Address of the leader like 0.0.0.0:9333, newer masters append the grpc port like 0.0.0.0:9333.19333
+    #[serde(default)]
+    pub leader: String,
+    #[serde(default)]
+    pub peers: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_volume_id: u32,
+}
+
+impl ClusterStatusResponse {
+    /// Address of the leader without the grpc port, parsable as [Master]
+    pub fn leader_address(&self) -> &str {
+        match self.leader.rsplit_once(':') {
+            Some((host, port)) => {
+                let http_port = port.split('.').next().unwrap_or_default();
+                &self.leader[..host.len() + 1 + http_port.len()]
+            }
+            None => &self.leader,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    static MASTER_HOST: &str = "localhost";
-    static MASTER_PORT: u16 = 8333;
+    use crate::testing::MockServer;
+    use crate::utils::{ReplicationType, ReplicationValues, TTLUnits, FID, TTL};
+
+    use super::{AssignKeyResponse, AssignKeyOptions, ClusterStatusResponse, GrowVolumeOptions, LookupVolumeOptions, Master, MasterErrors, VacuumVolumeOptions};
 
-    use crate::utils::FID;
+    #[test]
+    fn serialize_grow_volume() {
+        let options = GrowVolumeOptions {
+            count: Some(2),
+            collection: Some("tenant".to_string()),
+            replication: Some(ReplicationType::new(None, Some(ReplicationValues::OneReplica), None)),
+            ttl: Some(TTL { unit: TTLUnits::Day, value: 7 }),
+            data_center: Some("dc1".to_string()),
+            ..Default::default()
+        };
 
-    use super::{AssignKeyResponse, AssignKeyOptions, LookupVolumeOptions, Master};
+        assert_eq!(
+            "count=2&collection=tenant&replication=010&ttl=7d&dataCenter=dc1",
+            serde_qs::to_string(&options).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_resp_cluster_status() {
+        let data = r#"{
+            "IsLeader": false,
+            "Leader": "10.0.0.2:9333.19333",
+            "Peers": ["10.0.0.1:9333", "10.0.0.3:9333"],
+            "MaxVolumeId": 7
+        }"#;
+
+        let parsed = serde_json::from_str::<ClusterStatusResponse>(data).unwrap();
+        assert!(!parsed.is_leader);
+
+        assert!(Master::from_str(&parsed.leader).is_err());
+        let leader = Master::from_str(parsed.leader_address()).unwrap();
+        assert_eq!("http://10.0.0.2:9333", leader.to_string());
+    }
+
+    #[test]
+    fn parse_master_rejects_trailing_parts() {
+        assert!(Master::from_str("localhost:9333.garbage").is_err());
+        assert!(Master::from_str("host:9333.1.2").is_err());
+        assert!(Master::from_str("host:9333:1").is_err());
+        assert!(Master::from_str("host").is_err());
+        assert_eq!("http://host:9333", Master::from_str("host:9333").unwrap().to_string());
+    }
 
     #[test]
     fn parse_resp_assign_key() {
@@ -172,12 +633,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn assigned_fids() {
+        let data = r#"{
+            "count": 3,
+            "fid":"3,01637037d6",
+            "publicUrl":"1.1.1.1:9333",
+            "url":"1.2.2.2:3233",
+            "auth":"token"
+        }"#;
+
+        let parsed = serde_json::from_str::<AssignKeyResponse>(data).unwrap();
+        let fids: Vec<String> = parsed.fids().map(|a| a.fid.to_string()).collect();
+        assert_eq!(vec!["3,01637037d6", "3,01637037d6_1", "3,01637037d6_2"], fids);
+
+        let last = parsed.fids().last().unwrap();
+        assert_eq!("1.2.2.2:3233", last.location.url);
+        assert_eq!(Some("token".to_string()), last.auth);
+    }
+
     #[tokio::test]
     async fn call_assign_key() {
-        let master = Master {
-            host: MASTER_HOST.to_string(),
-            port: Some(MASTER_PORT),
-        };
+        let server = MockServer::start().await;
+        let master = server.master();
 
         let options: AssignKeyOptions = Default::default();
         let resp = master.assign_key(&Some(options)).await;
@@ -193,30 +671,26 @@ mod tests {
 
     #[tokio::test]
     async fn lookup_volume() {
-        let master = Master {
-            host: MASTER_HOST.to_string(),
-            port: Some(MASTER_PORT),
-        };
+        let server = MockServer::start().await;
+        let master = server.master();
 
         let options_assign: AssignKeyOptions = Default::default();
         let resp_assign = master.assign_key(&Some(options_assign)).await;
 
-        let fid: FID;
-
-        match resp_assign {
+        let fid: FID = match resp_assign {
             Ok(x) => {
                 println!("New assigned file id: {}", x.fid.to_string());
-                fid = x.fid
+                x.fid
             }
             _ => panic!("failed to assign key"),
-        }
+        };
 
         let options_lookup: LookupVolumeOptions = Default::default();
         let resp_lookup = master.lookup_volume(&fid, &Some(options_lookup)).await;
 
         match resp_lookup {
             Ok(x) => {
-                assert!(x.locations.len() > 0);
+                assert!(!x.locations.is_empty());
                 let location = &x.locations[0];
                 println!("New assigned file id: {}", location.public_url);
             }
@@ -224,4 +698,85 @@ mod tests {
         }
 
     }
+
+    #[tokio::test]
+    async fn lookup_volume_id() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
+        let resp = master.lookup_volume_id(1, &None).await.unwrap();
+        assert_eq!(format!("{}:{}", server.host(), server.port()), resp.locations[0].url);
+
+        assert!(master.lookup_volume_id(7, &None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn grow_volume() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
+        let options = GrowVolumeOptions {
+            count: Some(3),
+            collection: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        let resp = master.grow_volume(&Some(options)).await.unwrap();
+        assert_eq!(3, resp.count);
+
+        // a single data node cannot hold a replica on another rack
+        let options = GrowVolumeOptions {
+            replication: Some(ReplicationType::new(
+                None,
+                Some(ReplicationValues::OneReplica),
+                None,
+            )),
+            ..Default::default()
+        };
+        match master.grow_volume(&Some(options)).await {
+            Err(MasterErrors::InvalidRequest(e)) => {
+                assert_eq!(406, e.status);
+                assert!(e.message.contains("010"));
+            }
+            resp => panic!("expected an invalid request, got {:?}", resp),
+        }
+    }
+
+    #[tokio::test]
+    async fn vacuum_volumes() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
+        let resp = master.vacuum_volumes(&None).await.unwrap();
+        assert_eq!(8, resp.topology.max);
+
+        let options = VacuumVolumeOptions {
+            garbage_threshold: Some(1.5),
+            ..Default::default()
+        };
+        match master.vacuum_volumes(&Some(options)).await {
+            Err(MasterErrors::InvalidRequest(e)) => assert_eq!(406, e.status),
+            resp => panic!("expected an invalid request, got {:?}", resp),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_collection() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
+        let options = AssignKeyOptions {
+            collection: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        master.assign_key(&Some(options)).await.unwrap();
+        master.delete_collection("tenant").await.unwrap();
+
+        match master.delete_collection("tenant").await {
+            Err(MasterErrors::InvalidRequest(e)) => {
+                assert_eq!(400, e.status);
+                assert_eq!("collection tenant does not exist", e.message);
+            }
+            resp => panic!("expected an invalid request, got {:?}", resp),
+        }
+    }
 }
@@ -0,0 +1,320 @@
+use std::{fmt, future::Future, sync::Arc, time::Duration};
+
+use crate::{
+    filer::FilerErrors, master::MasterErrors, store::StoreErrors, utils, volume::VolumeErrors,
+};
+
+/// Coarse class of a failed request used to decide whether to retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The server could not be reached
+    Connect,
+    /// The request timed out
+    Timeout,
+    /// The connection broke while sending the request or reading the response
+    Interrupted,
+    /// The server answered with a 5xx StatusCode
+    ServerError(u16),
+    /// The file or volume does not exist
+    NotFound,
+    /// The server rejected the JWT or none was sent
+    Unauthorized,
+    /// Everything else like bad requests or parsing errors
+    Other,
+}
+
+/// Errors that can be classified for a [RetryPolicy]
+pub trait Retryable {
+    fn class(&self) -> ErrorClass;
+}
+
+fn reqwest_class(err: &reqwest::Error) -> ErrorClass {
+    if err.is_timeout() {
+        ErrorClass::Timeout
+    } else if err.is_connect() {
+        ErrorClass::Connect
+    } else if err.is_request() || err.is_body() {
+        ErrorClass::Interrupted
+    } else {
+        ErrorClass::Other
+    }
+}
+
+impl Retryable for MasterErrors {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::ReqwestError(e) => reqwest_class(e),
+            Self::ServerError(e) => ErrorClass::ServerError(e.status),
+            Self::VolumeNotFound(_) => ErrorClass::NotFound,
+            Self::Unauthorized(_) => ErrorClass::Unauthorized,
+            Self::NoMasterAvailable => ErrorClass::Connect,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+impl Retryable for VolumeErrors {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::ReqwestError(e) => reqwest_class(e),
+            Self::ServerError(e) => ErrorClass::ServerError(e.status),
+            Self::FileNotFound(_) => ErrorClass::NotFound,
+            Self::Unauthorized(_) => ErrorClass::Unauthorized,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+impl Retryable for FilerErrors {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::ReqwestError(e) => reqwest_class(e),
+            Self::ServerError(e) => ErrorClass::ServerError(e.status),
+            Self::NotFound(_) => ErrorClass::NotFound,
+            Self::Unauthorized(_) => ErrorClass::Unauthorized,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+impl Retryable for StoreErrors {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::MasterError(e) => e.class(),
+            Self::VolumeError(e) => e.class(),
+            Self::NoLocations(_) => ErrorClass::NotFound,
+            Self::GroupAssignFailed(e) => e.class(),
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// Decides how often and how long to wait between attempts of master, volume and filer requests
+///
+/// Only idempotent operations are retried: assigning keys, lookups, downloads,
+/// deletes, listings, creating directories and uploads of bytes to an already
+/// assigned file id or filer path. Multipart uploads consume their form, appends
+/// and moves are not idempotent, those are never retried.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use rusty_weed::{client::WeedClient, retry::{ErrorClass, RetryPolicy}};
+///
+/// let policy = RetryPolicy {
+///     max_attempts: 5,
+///     base_delay: Duration::from_millis(50),
+///     ..Default::default()
+/// }
+/// .retry_if(|class| matches!(class, ErrorClass::Connect | ErrorClass::ServerError(503)));
+///
+/// let client = WeedClient::builder().retry_policy(policy).build().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub base_delay: Duration,
+    /// Upper bound for the delay between attempts
+    pub max_delay: Duration,
+    /// Randomizes the delay between half and the full backoff
+    pub jitter: bool,
+    /// Custom decision which error classes are retried, see [retry_if](RetryPolicy::retry_if)
+    pub decide: Option<Arc<dyn Fn(ErrorClass) -> bool + Send + Sync>>,
+}
+
+impl Default for RetryPolicy {
+    /// A single attempt, so no retries
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            decide: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("custom_decision", &self.decide.is_some())
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter and the default decision
+    pub fn exponential(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the default decision which error classes are retried
+    pub fn retry_if<F>(mut self, decide: F) -> RetryPolicy
+    where
+        F: Fn(ErrorClass) -> bool + Send + Sync + 'static,
+    {
+        self.decide = Some(Arc::new(decide));
+        self
+    }
+
+    /// Whether an error of the class should be retried
+    ///
+    /// By default connection errors, timeouts, interrupted requests and 5xx responses
+    /// are retried, not found and all other errors are not.
+    pub fn should_retry(&self, class: ErrorClass) -> bool {
+        match &self.decide {
+            Some(decide) => decide(class),
+            None => matches!(
+                class,
+                ErrorClass::Connect
+                    | ErrorClass::Timeout
+                    | ErrorClass::Interrupted
+                    | ErrorClass::ServerError(_)
+            ),
+        }
+    }
+
+    /// Delay before the given retry, starting with 1 for the first retry
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let backoff = self.base_delay.saturating_mul(factor).min(self.max_delay);
+
+        match self.jitter {
+            true => {
+                let half = backoff / 2;
+                let range = (backoff - half).as_nanos() as u64;
+                match range {
+                    0 => half,
+                    _ => half + Duration::from_nanos(utils::random_u64() % range),
+                }
+            }
+            false => backoff,
+        }
+    }
+
+    /// Runs the operation until it succeeds, fails with an error that should
+    /// not be retried or runs out of attempts
+    pub async fn run<T, E, F, Fut>(&self, mut op: F) -> Result<T, E>
+    where
+        E: Retryable,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match op().await {
+                Err(err) if attempt < self.max_attempts && self.should_retry(err.class()) => {
+                    tokio::time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                resp => return resp,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use super::{ErrorClass, RetryPolicy, Retryable};
+
+    #[derive(Debug)]
+    struct TestError(ErrorClass);
+
+    impl Retryable for TestError {
+        fn class(&self) -> ErrorClass {
+            self.0
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(400), policy.delay(3));
+        assert_eq!(Duration::from_millis(500), policy.delay(9));
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        let delay = policy.delay(2);
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn retries_until_attempts_exhausted() {
+        let calls = Cell::new(0);
+
+        let resp: Result<(), TestError> = policy()
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                Err(TestError(ErrorClass::ServerError(503)))
+            })
+            .await;
+
+        assert!(resp.is_err());
+        assert_eq!(3, calls.get());
+    }
+
+    #[tokio::test]
+    async fn never_retries_not_found() {
+        let calls = Cell::new(0);
+
+        let resp: Result<(), TestError> = policy()
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                Err(TestError(ErrorClass::NotFound))
+            })
+            .await;
+
+        assert!(resp.is_err());
+        assert_eq!(1, calls.get());
+    }
+
+    #[tokio::test]
+    async fn custom_decision() {
+        let calls = Cell::new(0);
+
+        let resp: Result<(), TestError> = policy()
+            .retry_if(|class| class == ErrorClass::NotFound)
+            .run(|| async {
+                calls.set(calls.get() + 1);
+                match calls.get() {
+                    1 => Err(TestError(ErrorClass::NotFound)),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+
+        assert!(resp.is_ok());
+        assert_eq!(2, calls.get());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::utils::FID;
+
+/// Header of every token, SeaweedFS only verifies HS256
+const JWT_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+#[derive(Serialize)]
+struct FileIdClaims {
+    exp: u64,
+    fid: String,
+}
+
+/// Mints the JWTs SeaweedFS expects for volume requests from the keys in security.toml
+///
+/// Meant for trusted services that share the keys with the cluster. Without a signer
+/// use the token returned by [assign_key](crate::master::Master::assign_key) for writes.
+///
+/// # Example
+/// ```
+/// use rusty_weed::{security::JwtSigner, utils::FID};
+///
+/// // jwt.signing.key and jwt.signing.read.key
+/// let signer = JwtSigner::new("write secret").with_read_key("read secret");
+///
+/// let fid = FID::from_string("3,01637037d6").unwrap();
+/// let write = signer.sign_write(&fid);
+/// let read = signer.sign_read(&fid);
+/// ```
+#[derive(Clone)]
+pub struct JwtSigner {
+    write_key: Vec<u8>,
+    read_key: Option<Vec<u8>>,
+    /// Lifetime of write tokens, jwt.signing.expires_after_seconds
+    pub write_expires_after: Duration,
+    /// Lifetime of read tokens, jwt.signing.read.expires_after_seconds
+    pub read_expires_after: Duration,
+}
+
+impl fmt::Debug for JwtSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtSigner")
+            .field("read_key", &self.read_key.is_some())
+            .field("write_expires_after", &self.write_expires_after)
+            .field("read_expires_after", &self.read_expires_after)
+            .finish()
+    }
+}
+
+impl JwtSigner {
+    /// Creates a signer for writes, tokens expire after 10 seconds like in SeaweedFS
+    pub fn new(write_key: impl AsRef<[u8]>) -> JwtSigner {
+        JwtSigner {
+            write_key: write_key.as_ref().to_vec(),
+            read_key: None,
+            write_expires_after: Duration::from_secs(10),
+            read_expires_after: Duration::from_secs(60),
+        }
+    }
+
+    /// Adds the key for reads, only needed if the cluster protects reads as well
+    pub fn with_read_key(mut self, read_key: impl AsRef<[u8]>) -> JwtSigner {
+        self.read_key = Some(read_key.as_ref().to_vec());
+        self
+    }
+
+    /// Token for uploading or deleting the file id
+    pub fn sign_write(&self, fid: &FID) -> String {
+        sign(&self.write_key, fid, self.write_expires_after)
+    }
+
+    /// Token for reading the file id, None without a read key
+    pub fn sign_read(&self, fid: &FID) -> Option<String> {
+        self.read_key
+            .as_ref()
+            .map(|key| sign(key, fid, self.read_expires_after))
+    }
+}
+
+fn sign(key: &[u8], fid: &FID, expires_after: Duration) -> String {
+    let exp = SystemTime::now()
+        .checked_add(expires_after)
+        .unwrap_or_else(SystemTime::now)
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    // volume servers compare the claim against the file id without the count suffix
+    let claims = FileIdClaims {
+        exp,
+        fid: concat_string!(fid.volume_id.to_string(), ",", fid.file_string),
+    };
+    let header = URL_SAFE_NO_PAD.encode(JWT_HEADER);
+    let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default());
+    let message = concat_string!(header, ".", claims);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    concat_string!(message, ".", signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use crate::utils::FID;
+
+    use super::JwtSigner;
+
+    #[test]
+    fn sign_file_id() {
+        let signer = JwtSigner::new("secret");
+        let fid = FID::from_string("3,01637037d6_2").unwrap();
+
+        let token = signer.sign_write(&fid);
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(3, parts.len());
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!("3,01637037d6", claims["fid"]);
+        assert!(claims["exp"].as_u64().unwrap() > 0);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
+        mac.verify_slice(&URL_SAFE_NO_PAD.decode(parts[2]).unwrap())
+            .unwrap();
+
+        assert_eq!(None, signer.sign_read(&fid));
+    }
+}
@@ -0,0 +1,1369 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use futures_util::{
+    future::{join_all, try_join_all},
+    stream, Stream, StreamExt,
+};
+use thiserror::Error;
+use tokio::io::AsyncRead;
+
+use crate::{
+    cache::{CachedLocations, LocationCache},
+    chunk::{self, ChunkInfo, ChunkManifest, ChunkedPutOptions},
+    client::WeedClient,
+    cluster::MasterCluster,
+    error::{ResponseError, WeedError},
+    master::{AssignKeyOptions, LookupVolumeOptions, Master, MasterErrors},
+    retry::{ErrorClass, Retryable},
+    security::JwtSigner,
+    utils::{self, Location, FID},
+    volume::{
+        DeleteFileOptions, DeleteResponse, DeleteResult, GetFileOptions, UploadFileOptions,
+        Volume, VolumeErrors,
+    },
+};
+
+/// File ids sent in a single batch delete request
+const DELETE_BATCH_SIZE: usize = 1000;
+/// Lookups and batch delete requests running at the same time
+const DELETE_CONCURRENCY: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum StoreErrors {
+    #[error("master error")]
+    MasterError(#[from] MasterErrors),
+    #[error("volume error")]
+    VolumeError(#[from] VolumeErrors),
+    #[error("No locations found for volume {0}")]
+    NoLocations(u32),
+    #[error("Master assigned {assigned} of {requested} file ids")]
+    ShortAssignment { requested: u64, assigned: u64 },
+    #[error("Nothing to upload, the reader was empty")]
+    EmptyFile,
+    #[error("Assignment of the group of the item failed")]
+    GroupAssignFailed(#[source] Arc<StoreErrors>),
+    #[error("io error")]
+    IoError(#[from] std::io::Error),
+    #[error("chunk manifest parsing error")]
+    ManifestError(#[from] serde_json::Error),
+}
+
+impl WeedError for StoreErrors {
+    fn response(&self) -> Option<&ResponseError> {
+        match self {
+            Self::MasterError(e) => e.response(),
+            Self::VolumeError(e) => e.response(),
+            Self::GroupAssignFailed(e) => e.response(),
+            _ => None,
+        }
+    }
+}
+
+/// High level storage combining a [master](crate::master::Master) or a
+/// [cluster of masters](crate::cluster::MasterCluster) with its volumes
+///
+/// Hides the assign, lookup and upload round trips behind put, get and delete.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> Result<(), rusty_weed::store::StoreErrors> {
+/// use bytes::Bytes;
+/// use rusty_weed::{client::WeedClient, store::Store};
+///
+/// let client = WeedClient::new();
+/// let store = Store::new(client.master("localhost", Some(9333)));
+///
+/// let fid = store.put(&Bytes::from("Hello World!"), &None).await?;
+/// let data = store.get(&fid).await?;
+/// store.delete(&fid).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Store {
+    masters: MasterCluster,
+    cache: Option<LocationCache>,
+    read_strategy: ReadStrategy,
+    signer: Option<JwtSigner>,
+}
+
+/// Order in which the replicas of a volume are tried by [read](Store::read)
+#[derive(Clone, Debug, Default)]
+pub enum ReadStrategy {
+    /// In the order returned by the master
+    #[default]
+    InOrder,
+    /// Shuffled for every read to spread the load
+    Random,
+    /// Replicas in the given data center first, the rest in order
+    PreferDataCenter(String),
+}
+
+impl ReadStrategy {
+    /// Orders the locations according to the strategy
+    pub fn order<'a>(&self, locations: &'a [Location]) -> Vec<&'a Location> {
+        let mut ordered: Vec<&Location> = locations.iter().collect();
+
+        match self {
+            Self::InOrder => (),
+            Self::Random => {
+                for i in (1..ordered.len()).rev() {
+                    let j = (utils::random_u64() % (i as u64 + 1)) as usize;
+                    ordered.swap(i, j);
+                }
+            }
+            Self::PreferDataCenter(dc) => {
+                ordered.sort_by_key(|l| l.data_center.as_deref() != Some(dc.as_str()));
+            }
+        }
+
+        ordered
+    }
+}
+
+/// Return type of the store function [read](Store::read)
+#[derive(Debug)]
+pub struct ReplicaRead {
+    pub data: Bytes,
+    /// The replica that served the file
+    pub location: Location,
+    /// Replicas that failed before, in the order they were tried
+    pub failed: Vec<Location>,
+}
+
+/// Options for the store function [put](Store::put)
+#[derive(Debug, Default)]
+pub struct PutOptions {
+    pub assign: Option<AssignKeyOptions>,
+    pub upload: Option<UploadFileOptions>,
+}
+
+/// Options for the store functions [put_many](Store::put_many) and [put_many_stream](Store::put_many_stream)
+#[derive(Debug, Clone)]
+pub struct BulkPutOptions {
+    /// Number of file ids reserved by a single assignment
+    pub assign_count: usize,
+    /// Number of uploads running at the same time
+    pub concurrency: usize,
+    /// Used for every assignment, the count is set per group of items
+    pub assign: Option<AssignKeyOptions>,
+    /// Used for every upload, the JWT is set per file id
+    pub upload: Option<UploadFileOptions>,
+}
+
+impl Default for BulkPutOptions {
+    /// Assignments of 64 file ids with 8 uploads at the same time
+    fn default() -> Self {
+        BulkPutOptions {
+            assign_count: 64,
+            concurrency: 8,
+            assign: None,
+            upload: None,
+        }
+    }
+}
+
+/// An item of a bulk put waiting for its upload
+enum PendingPut {
+    Assigned {
+        index: usize,
+        fid: FID,
+        volume: Volume,
+        jwt: Option<String>,
+        data: Bytes,
+    },
+    /// The master reserved fewer file ids than its group has, uploaded with its own [put](Store::put)
+    Unassigned { index: usize, data: Bytes },
+    /// The assignment of its group failed, shares the error with the other items of the group
+    Failed {
+        index: usize,
+        error: Arc<StoreErrors>,
+    },
+}
+
+impl Store {
+    /// Creates a store from a master or a cluster, volumes share the http client of the masters
+    pub fn new(masters: impl Into<MasterCluster>) -> Store {
+        Store {
+            masters: masters.into(),
+            cache: None,
+            read_strategy: Default::default(),
+            signer: None,
+        }
+    }
+
+    /// Creates a store that caches volume locations
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use rusty_weed::{cache::LocationCache, master::Master, store::Store};
+    ///
+    /// let cache = LocationCache::new(Duration::from_secs(60), Duration::from_secs(5));
+    /// let store = Store::with_cache(Master::new("localhost", Some(9333)), cache);
+    /// ```
+    pub fn with_cache(masters: impl Into<MasterCluster>, cache: LocationCache) -> Store {
+        Store {
+            masters: masters.into(),
+            cache: Some(cache),
+            read_strategy: Default::default(),
+            signer: None,
+        }
+    }
+
+    /// Sets the order in which replicas are tried when reading
+    ///
+    /// # Example
+    /// ```
+    /// use rusty_weed::{master::Master, store::{ReadStrategy, Store}};
+    ///
+    /// let store = Store::new(Master::new("localhost", Some(9333)))
+    ///     .with_read_strategy(ReadStrategy::PreferDataCenter("dc1".to_string()));
+    /// ```
+    pub fn with_read_strategy(mut self, read_strategy: ReadStrategy) -> Store {
+        self.read_strategy = read_strategy;
+        self
+    }
+
+    /// Order in which replicas are tried when reading
+    pub fn read_strategy(&self) -> &ReadStrategy {
+        &self.read_strategy
+    }
+
+    /// Caches volume locations so reads mostly skip the master
+    pub fn cache(&self) -> Option<&LocationCache> {
+        self.cache.as_ref()
+    }
+
+    /// Signs reads and deletes, writes use the token of the assignment if there is one
+    ///
+    /// # Example
+    /// ```
+    /// use rusty_weed::{master::Master, security::JwtSigner, store::Store};
+    ///
+    /// let signer = JwtSigner::new("write secret").with_read_key("read secret");
+    /// let store = Store::new(Master::new("localhost", Some(9333))).with_signer(signer);
+    /// ```
+    pub fn with_signer(mut self, signer: JwtSigner) -> Store {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// The signer for reads and deletes
+    pub fn signer(&self) -> Option<&JwtSigner> {
+        self.signer.as_ref()
+    }
+
+    /// The shared client used for the masters and all volumes
+    pub fn client(&self) -> &WeedClient {
+        self.masters.client()
+    }
+
+    /// The masters the store assigns and looks up file ids with
+    pub fn masters(&self) -> &MasterCluster {
+        &self.masters
+    }
+
+    /// The last known leader or the first master if no request was made yet
+    pub fn master(&self) -> Option<Master> {
+        self.masters
+            .leader()
+            .or_else(|| self.masters.peers.first().cloned())
+    }
+
+    /// Assigns a file id, uploads the data to the assigned volume and returns the file id
+    ///
+    /// The JWT returned by the master is passed on to the volume server,
+    /// without one the [signer](Store::signer) is used
+    pub async fn put(
+        &self,
+        data: &Bytes,
+        options: &Option<PutOptions>,
+    ) -> Result<FID, StoreErrors> {
+        let (assign_options, upload_options) = match options {
+            Some(o) => (&o.assign, o.upload.clone()),
+            None => (&None, None),
+        };
+
+        let assigned = self.masters.assign_key(assign_options).await?;
+        let volume = self.client().volume_from_str(&assigned.location.url)?;
+
+        let mut upload_options = upload_options.unwrap_or_default();
+        if let Some(jwt) = self.write_token(&assigned.fid, &assigned.auth) {
+            upload_options.jwt = Some(jwt);
+        }
+
+        volume
+            .upload_file_bytes(&assigned.fid, data, &Some(upload_options))
+            .await?;
+
+        Ok(assigned.fid)
+    }
+
+    /// Splits the reader into chunks, uploads them and returns the file id of the chunk manifest
+    ///
+    /// Chunks are read in batches of [concurrency](ChunkedPutOptions::concurrency), every batch
+    /// gets one assignment with a matching count and is uploaded concurrently. If an upload
+    /// fails the chunks uploaded so far are deleted again. An empty reader is rejected with
+    /// [EmptyFile](StoreErrors::EmptyFile), use [put](Store::put) for empty files.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), rusty_weed::store::StoreErrors> {
+    /// use rusty_weed::{chunk::ChunkedPutOptions, master::Master, store::Store};
+    ///
+    /// let store = Store::new(Master::new("localhost", Some(9333)));
+    /// let file = tokio::fs::File::open("video.mp4").await?;
+    ///
+    /// let options = ChunkedPutOptions {
+    ///     name: Some("video.mp4".to_string()),
+    ///     mime: Some("video/mp4".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let fid = store.put_chunked(file, &Some(options)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn put_chunked<R>(
+        &self,
+        mut reader: R,
+        options: &Option<ChunkedPutOptions>,
+    ) -> Result<FID, StoreErrors>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let options = options.clone().unwrap_or_default();
+        let mut manifest = ChunkManifest {
+            name: options.name.clone(),
+            mime: options.mime.clone(),
+            ..Default::default()
+        };
+
+        let resp = self
+            .upload_chunks(&mut reader, &options, &mut manifest)
+            .await;
+
+        let resp = match resp {
+            Ok(()) if manifest.chunks.is_empty() => Err(StoreErrors::EmptyFile),
+            Ok(()) => self.upload_manifest(&manifest, &options).await,
+            Err(err) => Err(err),
+        };
+
+        if resp.is_err() {
+            let deletes = manifest.chunks.iter().map(|c| self.delete(&c.fid));
+            join_all(deletes).await;
+        }
+
+        resp
+    }
+
+    /// Uploads many small files and returns a result per item in the order of the items
+    ///
+    /// See [put_many_stream](Store::put_many_stream)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() {
+    /// use bytes::Bytes;
+    /// use rusty_weed::{master::Master, store::{BulkPutOptions, Store}};
+    ///
+    /// let store = Store::new(Master::new("localhost", Some(9333)));
+    /// let items = (0..1000).map(|i| Bytes::from(format!("item {}", i)));
+    ///
+    /// let options = BulkPutOptions {
+    ///     concurrency: 16,
+    ///     ..Default::default()
+    /// };
+    /// for (i, resp) in store.put_many(items, &Some(options)).await.into_iter().enumerate() {
+    ///     match resp {
+    ///         Ok(fid) => println!("{} stored as {}", i, fid.to_string()),
+    ///         Err(err) => println!("{} failed: {}", i, err),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn put_many<I>(
+        &self,
+        items: I,
+        options: &Option<BulkPutOptions>,
+    ) -> Vec<Result<FID, StoreErrors>>
+    where
+        I: IntoIterator<Item = Bytes>,
+        I::IntoIter: Send,
+    {
+        self.put_many_stream(stream::iter(items), options).await
+    }
+
+    /// Uploads a stream of small files and returns a result per item in the order of the items
+    ///
+    /// Items are grouped by [assign_count](BulkPutOptions::assign_count), every group gets one
+    /// assignment and uses the file ids `fid`, `fid_1`, `fid_2` and so on. Failed items do not
+    /// abort the others, all items of a failed assignment fail with
+    /// [GroupAssignFailed](StoreErrors::GroupAssignFailed) holding its error.
+    pub async fn put_many_stream<S>(
+        &self,
+        items: S,
+        options: &Option<BulkPutOptions>,
+    ) -> Vec<Result<FID, StoreErrors>>
+    where
+        S: Stream<Item = Bytes> + Send,
+    {
+        let options = options.clone().unwrap_or_default();
+
+        let mut results: Vec<(usize, Result<FID, StoreErrors>)> =
+            self.put_stream(items, &options).collect().await;
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, resp)| resp).collect()
+    }
+
+    /// Assigns and uploads the items of a bulk put, results are yielded as uploads finish
+    fn put_stream<'a, S>(
+        &'a self,
+        items: S,
+        options: &'a BulkPutOptions,
+    ) -> impl Stream<Item = (usize, Result<FID, StoreErrors>)> + Send + 'a
+    where
+        S: Stream<Item = Bytes> + Send + 'a,
+    {
+        items
+            .enumerate()
+            .chunks(options.assign_count.max(1))
+            .then(|group| self.assign_group(group, options))
+            .flat_map(stream::iter)
+            .map(|pending| self.upload_pending(pending, options))
+            .buffer_unordered(options.concurrency.max(1))
+    }
+
+    async fn assign_group(
+        &self,
+        group: Vec<(usize, Bytes)>,
+        options: &BulkPutOptions,
+    ) -> Vec<PendingPut> {
+        let mut assign = options.assign.clone().unwrap_or_default();
+        assign.count = Some(group.len() as u32);
+
+        let assigned = match self.masters.assign_key(&Some(assign)).await {
+            Ok(assigned) => assigned,
+            Err(err) => return failed(group, err.into()),
+        };
+        let volume = match self.client().volume_from_str(&assigned.location.url) {
+            Ok(volume) => volume,
+            Err(err) => return failed(group, err.into()),
+        };
+
+        let mut fids = assigned.fids();
+
+        group
+            .into_iter()
+            .map(|(index, data)| match fids.next() {
+                Some(assigned) => PendingPut::Assigned {
+                    index,
+                    jwt: self.write_token(&assigned.fid, &assigned.auth),
+                    fid: assigned.fid,
+                    volume: volume.clone(),
+                    data,
+                },
+                // the master reserved fewer file ids than requested
+                None => PendingPut::Unassigned { index, data },
+            })
+            .collect()
+    }
+
+    async fn upload_pending(
+        &self,
+        pending: PendingPut,
+        options: &BulkPutOptions,
+    ) -> (usize, Result<FID, StoreErrors>) {
+        match pending {
+            PendingPut::Assigned {
+                index,
+                fid,
+                volume,
+                jwt,
+                data,
+            } => {
+                let mut upload_options = options.upload.clone().unwrap_or_default();
+                if jwt.is_some() {
+                    upload_options.jwt = jwt;
+                }
+
+                let resp = volume
+                    .upload_file_bytes(&fid, &data, &Some(upload_options))
+                    .await;
+                (index, resp.map(|_| fid).map_err(StoreErrors::from))
+            }
+            PendingPut::Unassigned { index, data } => {
+                let put_options = PutOptions {
+                    assign: options.assign.clone(),
+                    upload: options.upload.clone(),
+                };
+                (index, self.put(&data, &Some(put_options)).await)
+            }
+            PendingPut::Failed { index, error } => {
+                (index, Err(StoreErrors::GroupAssignFailed(error)))
+            }
+        }
+    }
+
+    async fn upload_chunks<R>(
+        &self,
+        reader: &mut R,
+        options: &ChunkedPutOptions,
+        manifest: &mut ChunkManifest,
+    ) -> Result<(), StoreErrors>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let chunk_size = options.chunk_size.max(1);
+        let mut eof = false;
+
+        while !eof {
+            let mut batch = Vec::with_capacity(options.concurrency);
+            while batch.len() < options.concurrency.max(1) {
+                let data = chunk::read_chunk(reader, chunk_size).await?;
+                eof = data.len() < chunk_size;
+
+                if !data.is_empty() {
+                    batch.push(data);
+                }
+                if eof {
+                    break;
+                }
+            }
+
+            if batch.is_empty() {
+                break;
+            }
+
+            let mut assign = options.assign.clone().unwrap_or_default();
+            assign.count = Some(batch.len() as u32);
+
+            let assigned = self.masters.assign_key(&Some(assign)).await?;
+            let volume = self.client().volume_from_str(&assigned.location.url)?;
+            let upload_options = Some(UploadFileOptions {
+                jwt: self.write_token(&assigned.fid, &assigned.auth),
+                ..Default::default()
+            });
+
+            let fids: Vec<FID> = assigned.fids().map(|a| a.fid).collect();
+            if fids.len() < batch.len() {
+                return Err(StoreErrors::ShortAssignment {
+                    requested: batch.len() as u64,
+                    assigned: fids.len() as u64,
+                });
+            }
+
+            let uploads = fids
+                .iter()
+                .zip(batch.iter())
+                .map(|(fid, data)| volume.upload_file_bytes(fid, data, &upload_options));
+            let resp = try_join_all(uploads).await;
+
+            // keep track of the batch even if it failed so it gets cleaned up
+            for (fid, data) in fids.into_iter().zip(batch) {
+                manifest.chunks.push(ChunkInfo {
+                    fid,
+                    offset: manifest.size,
+                    size: data.len() as u64,
+                });
+                manifest.size += data.len() as u64;
+            }
+
+            resp?;
+        }
+
+        Ok(())
+    }
+
+    async fn upload_manifest(
+        &self,
+        manifest: &ChunkManifest,
+        options: &ChunkedPutOptions,
+    ) -> Result<FID, StoreErrors> {
+        let data = Bytes::from(serde_json::to_vec(manifest)?);
+
+        let put_options = PutOptions {
+            assign: options.assign.clone(),
+            upload: Some(UploadFileOptions {
+                cm: Some(true),
+                ..Default::default()
+            }),
+        };
+
+        self.put(&data, &Some(put_options)).await
+    }
+
+    /// Downloads the raw chunk manifest of a file uploaded with [put_chunked](Store::put_chunked)
+    pub async fn get_manifest(&self, fid: &FID) -> Result<ChunkManifest, StoreErrors> {
+        let (volume, _) = self.volume_for(fid).await?;
+
+        let options = GetFileOptions {
+            cm: Some(false),
+            jwt: self.read_token(fid),
+            ..Default::default()
+        };
+        let data = volume.get_file_bytes(fid, &Some(options)).await?;
+
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Deletes a file uploaded with [put_chunked](Store::put_chunked)
+    ///
+    /// The manifest goes first so the file is never left pointing to missing chunks.
+    /// Volume servers delete the chunks of a manifest themselves, deleting them
+    /// afterwards only cleans up leftovers and its errors are ignored.
+    pub async fn delete_chunked(&self, fid: &FID) -> Result<DeleteResponse, StoreErrors> {
+        let manifest = self.get_manifest(fid).await?;
+        let resp = self.delete(fid).await?;
+
+        let deletes = manifest.chunks.iter().map(|c| self.delete(&c.fid));
+        join_all(deletes).await;
+
+        Ok(resp)
+    }
+
+    /// Looks up the volume of the file id and downloads the file
+    ///
+    /// See [read](Store::read) for the replica failover
+    pub async fn get(&self, fid: &FID) -> Result<Bytes, StoreErrors> {
+        Ok(self.read(fid).await?.data)
+    }
+
+    /// Downloads the file from the first replica that answers
+    ///
+    /// Replicas are tried in the order of the [read strategy](Store::read_strategy),
+    /// connection errors, timeouts and 5xx responses fall over to the next replica.
+    pub async fn read(&self, fid: &FID) -> Result<ReplicaRead, StoreErrors> {
+        let (locations, cached) = self.locations(fid).await?;
+
+        let resp = self.read_from(fid, &locations).await;
+
+        if let Err(StoreErrors::VolumeError(err)) = &resp {
+            if cached && self.is_stale_location(fid, err).await {
+                self.invalidate(fid);
+                let (locations, _) = self.locations(fid).await?;
+                return self.read_from(fid, &locations).await;
+            }
+        }
+
+        resp
+    }
+
+    async fn read_from(
+        &self,
+        fid: &FID,
+        locations: &[Location],
+    ) -> Result<ReplicaRead, StoreErrors> {
+        let mut failed = Vec::new();
+        let mut last_err = None;
+        let options = Some(GetFileOptions {
+            jwt: self.read_token(fid),
+            ..Default::default()
+        });
+
+        for location in self.read_strategy.order(locations) {
+            let volume = self.client().volume_from_str(&location.url)?;
+
+            match volume.get_file_bytes(fid, &options).await {
+                Ok(data) => {
+                    return Ok(ReplicaRead {
+                        data,
+                        location: location.clone(),
+                        failed,
+                    })
+                }
+                Err(err) if is_replica_failure(&err) => {
+                    failed.push(location.clone());
+                    last_err = Some(err);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        match last_err {
+            Some(err) => Err(err.into()),
+            None => Err(StoreErrors::NoLocations(fid.volume_id)),
+        }
+    }
+
+    /// Looks up the volume of the file id and deletes the file
+    pub async fn delete(&self, fid: &FID) -> Result<DeleteResponse, StoreErrors> {
+        let (volume, cached) = self.volume_for(fid).await?;
+        let options = Some(DeleteFileOptions {
+            jwt: self.write_token(fid, &None),
+        });
+
+        let resp = volume.delete_file_with(fid, &options).await;
+
+        if let Err(err) = &resp {
+            if cached && self.is_stale_location(fid, err).await {
+                self.invalidate(fid);
+                let (volume, _) = self.volume_for(fid).await?;
+                return Ok(volume.delete_file_with(fid, &options).await?);
+            }
+        }
+
+        Ok(resp?)
+    }
+
+    /// Deletes many files with one lookup per volume and batch requests per volume server
+    ///
+    /// Every replica of a volume gets the batch, the returned results are in the order of the
+    /// file ids. A file counts as deleted if no replica failed, see [DeleteResult::is_gone].
+    /// Files of volumes without locations have no status, retry them later.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run(expired: Vec<rusty_weed::utils::FID>) {
+    /// use rusty_weed::{master::Master, store::Store};
+    ///
+    /// let store = Store::new(Master::new("localhost", Some(9333)));
+    ///
+    /// for result in store.delete_many(&expired).await {
+    ///     if !result.is_gone() {
+    ///         println!("{} not deleted: {:?}", result.fid.to_string(), result.error);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn delete_many(&self, fids: &[FID]) -> Vec<DeleteResult> {
+        let mut by_volume: HashMap<u32, HashSet<FID>> = HashMap::new();
+        for fid in fids {
+            by_volume.entry(fid.volume_id).or_default().insert(fid.clone());
+        }
+
+        let lookups: Vec<_> = stream::iter(by_volume.into_values())
+            .map(|fids| async move {
+                let fids: Vec<FID> = fids.into_iter().collect();
+                (self.locations(&fids[0]).await, fids)
+            })
+            .buffer_unordered(DELETE_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut results: HashMap<FID, DeleteResult> = HashMap::new();
+        let mut by_server: HashMap<String, Vec<FID>> = HashMap::new();
+
+        for (resp, fids) in lookups {
+            match resp {
+                Ok((locations, _)) if !locations.is_empty() => {
+                    for location in locations {
+                        by_server
+                            .entry(location.url)
+                            .or_default()
+                            .extend(fids.iter().cloned());
+                    }
+                }
+                Ok(_) => {
+                    let err = StoreErrors::NoLocations(fids[0].volume_id);
+                    for fid in fids {
+                        merge_delete_result(&mut results, failed_delete(fid, &err));
+                    }
+                }
+                Err(err) => {
+                    for fid in fids {
+                        merge_delete_result(&mut results, failed_delete(fid, &err));
+                    }
+                }
+            }
+        }
+
+        let batches = by_server.into_iter().flat_map(|(url, fids)| {
+            fids.chunks(DELETE_BATCH_SIZE)
+                .map(|batch| (url.clone(), batch.to_vec()))
+                .collect::<Vec<_>>()
+        });
+
+        let responses: Vec<_> = stream::iter(batches)
+            .map(|(url, batch)| async move {
+                let resp = match self.client().volume_from_str(&url) {
+                    Ok(volume) => volume.delete_many(&batch).await,
+                    Err(err) => Err(err),
+                };
+                (resp, batch)
+            })
+            .buffer_unordered(DELETE_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (resp, batch) in responses {
+            match resp {
+                Ok(deleted) => {
+                    for result in deleted {
+                        merge_delete_result(&mut results, result);
+                    }
+                }
+                Err(err) => {
+                    let err = StoreErrors::from(err);
+                    for fid in batch {
+                        merge_delete_result(&mut results, failed_delete(fid, &err));
+                    }
+                }
+            }
+        }
+
+        fids.iter()
+            .map(|fid| match results.get(fid) {
+                Some(result) => result.clone(),
+                None => DeleteResult {
+                    fid: fid.clone(),
+                    status: None,
+                    size: 0,
+                    error: Some("missing in the response of the volume server".to_string()),
+                },
+            })
+            .collect()
+    }
+
+    /// The token of the assignment or one minted by the signer
+    fn write_token(&self, fid: &FID, assigned: &Option<String>) -> Option<String> {
+        match (assigned, &self.signer) {
+            (Some(jwt), _) => Some(jwt.clone()),
+            (None, Some(signer)) => Some(signer.sign_write(fid)),
+            (None, None) => None,
+        }
+    }
+
+    fn read_token(&self, fid: &FID) -> Option<String> {
+        self.signer.as_ref().and_then(|s| s.sign_read(fid))
+    }
+
+    /// Whether a volume error hints that the cached location is outdated,
+    /// the volume server is gone or the volume moved away
+    ///
+    /// A 404 only counts if the volume server that answered no longer lists the volume
+    /// in its [status](Volume::status), a file that was simply deleted keeps the cached
+    /// location. Only if the status cannot be read the message of the 404 decides.
+    async fn is_stale_location(&self, fid: &FID, err: &VolumeErrors) -> bool {
+        let resp = match err {
+            VolumeErrors::ReqwestError(e) => return e.is_connect() || e.is_timeout(),
+            VolumeErrors::FileNotFound(resp) if resp.status == 404 => resp,
+            _ => return false,
+        };
+
+        let server = reqwest::Url::parse(&resp.url)
+            .ok()
+            .and_then(|url| Some(self.client().volume(url.host_str()?, url.port())));
+        let status = match server {
+            Some(volume) => volume.status().await.ok(),
+            None => None,
+        };
+
+        match status {
+            Some(status) => !status.holds(fid.volume_id),
+            None => is_missing_volume(&resp.message),
+        }
+    }
+
+    /// Removes the cached locations of the volume holding the file id
+    pub fn invalidate(&self, fid: &FID) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(fid.volume_id);
+        }
+    }
+
+    /// Returns the first location of the volume holding the file id
+    /// and whether it was taken from the cache
+    async fn volume_for(&self, fid: &FID) -> Result<(Volume, bool), StoreErrors> {
+        let (locations, cached) = self.locations(fid).await?;
+
+        match locations.first() {
+            Some(location) => Ok((self.client().volume_from_str(&location.url)?, cached)),
+            None => Err(StoreErrors::NoLocations(fid.volume_id)),
+        }
+    }
+
+    /// Returns the locations of the volume holding the file id
+    /// and whether they were taken from the cache
+    async fn locations(&self, fid: &FID) -> Result<(Vec<Location>, bool), StoreErrors> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return Ok((self.lookup(fid).await?, false)),
+        };
+
+        match cache.get(fid.volume_id) {
+            Some(CachedLocations::Found(locations)) => return Ok((locations, true)),
+            Some(CachedLocations::Missing) => return Err(StoreErrors::NoLocations(fid.volume_id)),
+            None => (),
+        }
+
+        match self.lookup(fid).await {
+            Ok(locations) if locations.is_empty() => {
+                cache.insert_missing(fid.volume_id);
+                Ok((locations, false))
+            }
+            Ok(locations) => {
+                cache.insert(fid.volume_id, locations.clone());
+                Ok((locations, false))
+            }
+            Err(StoreErrors::MasterError(MasterErrors::VolumeNotFound(_))) => {
+                cache.insert_missing(fid.volume_id);
+                Err(StoreErrors::NoLocations(fid.volume_id))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn lookup(&self, fid: &FID) -> Result<Vec<Location>, StoreErrors> {
+        let options: LookupVolumeOptions = Default::default();
+        let resp = self.masters.lookup_volume(fid, &Some(options)).await?;
+
+        Ok(resp.locations)
+    }
+}
+
+/// Result of a file whose volume could not be looked up or whose batch failed
+fn failed_delete(fid: FID, err: &StoreErrors) -> DeleteResult {
+    let status = match err {
+        // no volume server was asked, the file may still exist
+        StoreErrors::NoLocations(_) | StoreErrors::MasterError(_) => None,
+        err => err.status(),
+    };
+
+    // the messages of master and volume errors are in their sources
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        message = concat_string!(message, ": ", err.to_string());
+        source = err.source();
+    }
+
+    DeleteResult {
+        fid,
+        status,
+        size: 0,
+        error: Some(message),
+    }
+}
+
+/// Keeps the result of the worst replica, a failure beats a deletion which beats a missing file
+fn merge_delete_result(results: &mut HashMap<FID, DeleteResult>, result: DeleteResult) {
+    let rank = |r: &DeleteResult| match (r.is_deleted(), r.is_gone()) {
+        (true, _) => 1,
+        (false, true) => 0,
+        (false, false) => 2,
+    };
+
+    match results.get(&result.fid) {
+        Some(existing) if rank(existing) >= rank(&result) => (),
+        _ => {
+            results.insert(result.fid.clone(), result);
+        }
+    }
+}
+
+fn failed(group: Vec<(usize, Bytes)>, error: StoreErrors) -> Vec<PendingPut> {
+    let error = Arc::new(error);
+
+    group
+        .into_iter()
+        .map(|(index, _)| PendingPut::Failed {
+            index,
+            error: error.clone(),
+        })
+        .collect()
+}
+
+/// Fallback of [is_stale_location](Store::is_stale_location) if the volume server
+/// does not answer /status, whether the message of a 404 names the volume instead
+/// of the file like volume 3 not found
+fn is_missing_volume(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("volume") && message.contains("not found")
+}
+
+/// Whether a volume error should fall over to the next replica
+fn is_replica_failure(err: &VolumeErrors) -> bool {
+    matches!(
+        err.class(),
+        ErrorClass::Connect
+            | ErrorClass::Timeout
+            | ErrorClass::Interrupted
+            | ErrorClass::ServerError(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use bytes::Bytes;
+    use futures_util::stream;
+    use reqwest::multipart::Form;
+
+    use crate::{
+        cache::{CachedLocations, LocationCache},
+        chunk::ChunkedPutOptions,
+        client::WeedClient,
+        error::WeedError,
+        health,
+        master::Master,
+        retry::{ErrorClass, RetryPolicy, Retryable},
+        testing::{Faults, MockServer},
+        utils::{ByteRange, Location, FID},
+        volume::PartialReadOptions,
+    };
+
+    use super::{BulkPutOptions, ReadStrategy, Store, StoreErrors};
+
+    fn assert_send<T: Send>(_: T) {}
+
+    /// Fails to compile if a public future can not be passed to tokio::spawn
+    #[test]
+    fn futures_are_send() {
+        let master = Master::new("localhost", Some(9333));
+        let store = Store::new(master.clone());
+        let volume = store.client().volume("localhost", Some(8080));
+        let filer = store.client().filer("localhost", Some(8888));
+        let fid = FID::from_string("3,01637037d6").unwrap();
+        let data = Bytes::from("Hello World!");
+
+        assert_send(store.put(&data, &None));
+        assert_send(store.put_chunked(&b"Hello World!"[..], &None));
+        assert_send(store.put_many(vec![data.clone()], &None));
+        assert_send(store.put_many_stream(stream::iter(vec![data.clone()]), &None));
+        assert_send(store.get(&fid));
+        assert_send(store.read(&fid));
+        assert_send(store.get_manifest(&fid));
+        assert_send(store.delete(&fid));
+        assert_send(store.delete_chunked(&fid));
+        assert_send(store.delete_many(std::slice::from_ref(&fid)));
+
+        assert_send(master.assign_key(&None));
+        assert_send(master.lookup_volume(&fid, &None));
+        assert_send(master.topology());
+        assert_send(store.masters().assign_key(&None));
+        assert_send(store.masters().lookup_volume(&fid, &None));
+        assert_send(health::check(store.masters(), &None));
+
+        assert_send(volume.get_file_stream(&fid, &None));
+        assert_send(volume.get_file_bytes(&fid, &None));
+        assert_send(volume.get_file_range(&fid, ByteRange::From(0), &None));
+        assert_send(volume.get_file_partial(&fid, &PartialReadOptions::default(), &None));
+        assert_send(volume.head_file(&fid, &None));
+        assert_send(volume.status());
+        assert_send(volume.delete_file(&fid));
+        assert_send(volume.delete_many(std::slice::from_ref(&fid)));
+        assert_send(volume.upload_file_bytes(&fid, &data, &None));
+        assert_send(volume.upload_file_form(&fid, Form::new(), &None));
+        assert_send(volume.upload_stream(&fid, &b"Hello World!"[..], Some(12), &None));
+        assert_send(volume.upload_path(&fid, Path::new("hello.txt"), &None));
+
+        assert_send(filer.upload_file_bytes("/hello.txt", &data, &None));
+        assert_send(filer.get_file_bytes("/hello.txt", &None));
+        assert_send(filer.list_directory("/", &None));
+        assert_send(filer.create_directory("/dir"));
+        assert_send(filer.delete("/hello.txt", &None));
+        assert_send(filer.move_path("/hello.txt", "/world.txt"));
+        assert_send(filer.get_metadata("/hello.txt"));
+    }
+
+    fn location(url: &str, data_center: Option<&str>) -> Location {
+        Location {
+            public_url: url.to_string(),
+            url: url.to_string(),
+            data_center: data_center.map(|dc| dc.to_string()),
+        }
+    }
+
+    #[test]
+    fn read_strategy_order() {
+        let locations = vec![
+            location("1.1.1.1:8080", Some("dc1")),
+            location("2.2.2.2:8080", Some("dc2")),
+            location("3.3.3.3:8080", None),
+            location("4.4.4.4:8080", Some("dc2")),
+        ];
+
+        let ordered = ReadStrategy::InOrder.order(&locations);
+        assert_eq!("1.1.1.1:8080", ordered[0].url);
+
+        let ordered = ReadStrategy::PreferDataCenter("dc2".to_string()).order(&locations);
+        let urls: Vec<&str> = ordered.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(
+            vec![
+                "2.2.2.2:8080",
+                "4.4.4.4:8080",
+                "1.1.1.1:8080",
+                "3.3.3.3:8080"
+            ],
+            urls
+        );
+
+        let ordered = ReadStrategy::Random.order(&locations);
+        assert_eq!(locations.len(), ordered.len());
+    }
+
+    #[tokio::test]
+    async fn put_get_delete() {
+        let server = MockServer::start().await;
+        let store = Store::new(server.master());
+
+        let data = Bytes::from("Hello World!");
+        let fid = match store.put(&data, &None).await {
+            Ok(fid) => fid,
+            Err(err) => {
+                println!("{}", err);
+                panic!("failed to put file");
+            }
+        };
+        assert_eq!(
+            Some(server.master().to_string()),
+            store.master().map(|m| m.to_string())
+        );
+
+        match store.get(&fid).await {
+            Ok(x) => assert_eq!(data, x),
+            Err(err) => {
+                println!("{}", err);
+                panic!("failed to get file");
+            }
+        }
+
+        if let Err(err) = store.delete(&fid).await {
+            println!("{}", err);
+            panic!("failed to delete file");
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_round_trip() {
+        let server = MockServer::start().await;
+        let store = Store::new(server.master());
+
+        let data: Vec<u8> = (0..100).collect();
+        let options = ChunkedPutOptions {
+            chunk_size: 16,
+            concurrency: 3,
+            name: Some("numbers.bin".to_string()),
+            ..Default::default()
+        };
+        let fid = store.put_chunked(&data[..], &Some(options)).await.unwrap();
+
+        let manifest = store.get_manifest(&fid).await.unwrap();
+        assert_eq!(Some("numbers.bin"), manifest.name.as_deref());
+        assert_eq!(100, manifest.size);
+        assert_eq!(7, manifest.chunks.len());
+        assert_eq!(96, manifest.chunks[6].offset);
+        assert_eq!(Bytes::from(data), store.get(&fid).await.unwrap());
+
+        store.delete_chunked(&fid).await.unwrap();
+        assert!(!server.contains(&fid));
+        assert!(manifest.chunks.iter().all(|c| !server.contains(&c.fid)));
+
+        let before = server.requests();
+        let err = store.put_chunked(&b""[..], &None).await.unwrap_err();
+        assert!(matches!(err, StoreErrors::EmptyFile));
+        assert_eq!(before, server.requests());
+    }
+
+    #[tokio::test]
+    async fn relookup_moved_volume() {
+        let server = MockServer::start().await;
+        let target = MockServer::start().await;
+        let cache = LocationCache::new(Duration::from_secs(60), Duration::from_secs(5));
+        let store = Store::with_cache(server.master(), cache);
+
+        let data = Bytes::from("Hello World!");
+        let fid = store.put(&data, &None).await.unwrap();
+        assert_eq!(data, store.get(&fid).await.unwrap());
+
+        server.move_volume(&target);
+        let target_url = format!("{}:{}", target.host(), target.port());
+
+        // the cached location answers 404 and no longer lists the volume in its status,
+        // the store looks up the volume again
+        let before = server.requests();
+        let read = store.read(&fid).await.unwrap();
+        assert_eq!(data, read.data);
+        assert_eq!(target_url, read.location.url);
+        assert_eq!(3, server.requests() - before);
+        assert_eq!(1, server.requests_to("/status"));
+        assert_eq!(1, target.requests());
+
+        match store.cache().unwrap().get(fid.volume_id) {
+            Some(CachedLocations::Found(locations)) => assert_eq!(target_url, locations[0].url),
+            cached => panic!("expected the new location in the cache, got {:?}", cached),
+        }
+    }
+
+    #[tokio::test]
+    async fn deleted_file_keeps_cached_location() {
+        let server = MockServer::start().await;
+        let cache = LocationCache::new(Duration::from_secs(60), Duration::from_secs(5));
+        let store = Store::with_cache(server.master(), cache);
+
+        let fid = store.put(&Bytes::from("Hello World!"), &None).await.unwrap();
+        store.delete(&fid).await.unwrap();
+
+        // the file is gone but the status still lists the volume, no lookup on the master
+        let before = server.requests();
+        let err = store.get(&fid).await.unwrap_err();
+        assert!(err.is_not_found());
+        assert_eq!(2, server.requests() - before);
+        assert_eq!(1, server.requests_to("/status"));
+        assert_eq!(1, server.requests_to("/dir/lookup"));
+    }
+
+    #[tokio::test]
+    async fn read_fails_over_to_next_replica() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy::exponential(1))
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client.clone()).await;
+        let broken = MockServer::start_with_client(client).await;
+        let store = Store::new(server.master());
+
+        let data = Bytes::from("Hello World!");
+        let fid = store.put(&data, &None).await.unwrap();
+
+        // nothing listens on the first replica, the second drops the connection
+        let dead = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let dropping = format!("{}:{}", broken.host(), broken.port());
+        let url = format!("{}:{}", server.host(), server.port());
+        server.set_locations(&[dead.clone(), dropping.clone(), url.clone()]);
+        broken.inject(Faults {
+            dropped_connections: 1,
+            ..Default::default()
+        });
+
+        let read = store.read(&fid).await.unwrap();
+        assert_eq!(data, read.data);
+        assert_eq!(url, read.location.url);
+
+        let failed: Vec<&str> = read.failed.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(vec![dead.as_str(), dropping.as_str()], failed);
+    }
+
+    #[tokio::test]
+    async fn put_many_in_groups() {
+        let server = MockServer::start().await;
+        let store = Store::new(server.master());
+
+        let items: Vec<Bytes> = (0..10).map(|i| Bytes::from(format!("item {}", i))).collect();
+        let options = BulkPutOptions {
+            assign_count: 4,
+            concurrency: 3,
+            ..Default::default()
+        };
+        let results = store.put_many(items.clone(), &Some(options)).await;
+
+        assert_eq!(3, server.requests_to("/dir/assign"));
+        assert_eq!(10, results.len());
+
+        // groups of 4, 4 and 2 items share the key of their assignment
+        let fids: Vec<FID> = results.into_iter().map(Result::unwrap).collect();
+        let counts: Vec<Option<u64>> = fids.iter().map(|fid| fid.count).collect();
+        assert_eq!(&[None, Some(1), Some(2), Some(3)], &counts[..4]);
+        assert_eq!(&[None, Some(1), Some(2), Some(3)], &counts[4..8]);
+        assert_eq!(&[None, Some(1)], &counts[8..]);
+        assert_eq!(fids[0].file_string, fids[3].file_string);
+        assert_ne!(fids[0].file_string, fids[4].file_string);
+
+        for (data, fid) in items.iter().zip(fids) {
+            assert_eq!(data, &store.get(&fid).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn put_many_fails_group_of_failed_assignment() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy::exponential(1))
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client).await;
+        let store = Store::new(server.master());
+
+        server.inject(Faults {
+            server_errors: 1,
+            path_prefix: Some("/dir/assign".to_string()),
+            ..Default::default()
+        });
+        let options = BulkPutOptions {
+            assign_count: 2,
+            concurrency: 1,
+            ..Default::default()
+        };
+        let items = vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")];
+        let results = store.put_many(items, &Some(options)).await;
+
+        assert_eq!(2, server.requests_to("/dir/assign"));
+        for resp in &results[..2] {
+            match resp {
+                Err(err @ StoreErrors::GroupAssignFailed(_)) => {
+                    assert_eq!(ErrorClass::ServerError(500), err.class());
+                    assert_eq!("injected server error", err.response().unwrap().message);
+                }
+                resp => panic!("expected the failed assignment, got {:?}", resp),
+            }
+        }
+        assert!(results[2].is_ok());
+    }
+
+    #[tokio::test]
+    async fn put_many_keeps_going() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy::exponential(1))
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client).await;
+        let store = Store::new(server.master());
+
+        server.inject(Faults {
+            server_errors: 1,
+            path_prefix: Some("/1,".to_string()),
+            ..Default::default()
+        });
+        let options = BulkPutOptions {
+            concurrency: 1,
+            ..Default::default()
+        };
+        let items = vec![Bytes::from("a"), Bytes::from("b"), Bytes::from("c")];
+        let results = store.put_many(items, &Some(options)).await;
+
+        assert_eq!(3, results.len());
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok() && results[2].is_ok());
+    }
+
+    #[tokio::test]
+    async fn delete_many_in_batches() {
+        let server = MockServer::start().await;
+        let store = Store::new(server.master());
+
+        let items = (0..5).map(|i| Bytes::from(format!("item {}", i)));
+        let mut fids: Vec<_> = store
+            .put_many(items, &None)
+            .await
+            .into_iter()
+            .map(|resp| resp.unwrap())
+            .collect();
+        store.delete(&fids[4]).await.unwrap();
+        fids.push("7,01637037d6".parse().unwrap());
+        fids.push(fids[0].clone());
+
+        let before = server.requests();
+        let results = store.delete_many(&fids).await;
+
+        // lookups of volume 1 and 7 and a single batch
+        assert_eq!(3, server.requests() - before);
+        assert_eq!(fids.len(), results.len());
+        assert!(results[..4].iter().all(|r| r.is_deleted() && r.size == 6));
+        assert!(results[4].is_gone() && !results[4].is_deleted());
+        // no volume server holds volume 7, the file is not known to be gone
+        assert_eq!(None, results[5].status);
+        assert!(!results[5].is_gone());
+        assert!(results[6].is_deleted());
+        assert!(fids[..4].iter().all(|fid| !server.contains(fid)));
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+use crate::volume::{FileMeta, VolumeErrors};
+
+/// Body of a file downloaded from a volume server as a stream of bytes
+///
+/// The file is never buffered as a whole, use [into_reader](FileStream::into_reader)
+/// to pipe it into anything expecting an [AsyncRead].
+pub struct FileStream {
+    content_length: Option<u64>,
+    meta: FileMeta,
+    inner: Pin<Box<dyn Stream<Item = Result<Bytes, VolumeErrors>> + Send>>,
+}
+
+impl FileStream {
+    pub(crate) fn new(resp: reqwest::Response) -> FileStream {
+        FileStream {
+            content_length: resp.content_length(),
+            meta: FileMeta::from_headers(resp.headers()),
+            inner: Box::pin(resp.bytes_stream().map_err(VolumeErrors::from)),
+        }
+    }
+
+    /// Size of the file if the volume server sent a Content-Length
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Metadata sent with the file like the content type and the custom pairs
+    pub fn meta(&self) -> &FileMeta {
+        &self.meta
+    }
+
+    /// Turns the stream into an [AsyncRead], errors become [io::Error]s
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use rusty_weed::{utils::FID, volume::Volume};
+    ///
+    /// let volume = Volume::new("localhost", Some(8080));
+    /// let fid = FID::from_string("3,01637037d6")?;
+    ///
+    /// let mut reader = volume.get_file_stream(&fid, &None).await?.into_reader();
+    /// let mut file = tokio::fs::File::create("/tmp/download").await?;
+    /// tokio::io::copy(&mut reader, &mut file).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_reader(self) -> impl AsyncRead + Send + Unpin {
+        StreamReader::new(self.map_err(io::Error::other))
+    }
+}
+
+impl Stream for FileStream {
+    type Item = Result<Bytes, VolumeErrors>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+    use tokio::io::AsyncReadExt;
+
+    use super::FileStream;
+
+    fn response(body: &'static str) -> reqwest::Response {
+        reqwest::Response::from(http::Response::new(body))
+    }
+
+    #[tokio::test]
+    async fn stream_body() {
+        let stream = FileStream::new(response("Hello World!"));
+        let chunks: Vec<_> = stream.try_collect().await.unwrap();
+
+        assert_eq!(b"Hello World!".to_vec(), chunks.concat());
+    }
+
+    #[test]
+    fn meta_from_headers() {
+        let resp = http::Response::builder()
+            .header("Seaweed-Owner", "alice")
+            .body("Hello World!")
+            .unwrap();
+        let stream = FileStream::new(reqwest::Response::from(resp));
+
+        assert_eq!(Some(&"alice".to_string()), stream.meta().pairs.get("owner"));
+    }
+
+    #[tokio::test]
+    async fn read_body() {
+        let mut reader = FileStream::new(response("Hello World!")).into_reader();
+
+        let mut body = String::new();
+        reader.read_to_string(&mut body).await.unwrap();
+        assert_eq!("Hello World!", body);
+    }
+}
@@ -0,0 +1,1240 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+    hash::{Hash, Hasher},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+use crate::{
+    chunk::ChunkManifest,
+    client::WeedClient,
+    filer::Filer,
+    master::Master,
+    utils::{ByteRange, FID},
+    volume::Volume,
+};
+
+/// Id of the only volume of the mock server
+const VOLUME_ID: u32 = 1;
+
+/// Faults injected into the following requests of a [MockServer]
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    /// Delay before every request is answered
+    pub latency: Option<Duration>,
+    /// Number of requests answered with 500 Internal Server Error
+    pub server_errors: u32,
+    /// Number of requests whose connection is closed without an answer
+    pub dropped_connections: u32,
+    /// Number of requests answered with 200 OK and a body that is not valid JSON
+    pub malformed_bodies: u32,
+    /// Only requests with a path starting with this prefix are affected, like /dir/assign
+    pub path_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredFile {
+    data: Bytes,
+    e_tag: String,
+    content_type: Option<String>,
+    file_name: Option<String>,
+    pairs: Vec<(String, String)>,
+    modified: SystemTime,
+    /// Uploaded with cm=true, reads return the joined chunks
+    manifest: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Address of the server itself
+    url: String,
+    /// Addresses handed out as locations of the volume
+    locations: Vec<String>,
+    /// Leader reported by /cluster/status, the server itself if not set
+    leader: Option<String>,
+    next_key: u64,
+    /// Collections created by assigns and grows
+    collections: HashSet<String>,
+    volume: HashMap<String, StoredFile>,
+    /// Set once the volume moved to another server, which answers volume not found from then on
+    moved: bool,
+    filer: HashMap<String, StoredFile>,
+    /// Explicitly created filer directories, parents of files exist implicitly
+    dirs: HashSet<String>,
+    faults: Faults,
+    /// Paths of all requests received so far
+    requests: Vec<String>,
+}
+
+/// In-memory fake of a SeaweedFS master, volume server and filer on a single local port
+///
+/// Implements assign, lookup, growing and vacuuming volumes, deleting collections, upload, download, head and delete of file ids including
+/// chunk manifests with the StatusCodes and JSON bodies of SeaweedFS, files and directories on the filer and [Faults]
+/// to test failure handling. All file ids live on volume 1, everything else is unknown.
+///
+/// # Example
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use bytes::Bytes;
+/// use rusty_weed::{store::Store, testing::{Faults, MockServer}};
+///
+/// let server = MockServer::start().await;
+/// let store = Store::new(server.master());
+///
+/// let fid = store.put(&Bytes::from("Hello World!"), &None).await.unwrap();
+/// assert_eq!(Bytes::from("Hello World!"), store.get(&fid).await.unwrap());
+///
+/// server.inject(Faults { server_errors: 1, ..Default::default() });
+/// assert!(store.get(&fid).await.is_err());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    client: WeedClient,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Starts the server on a random local port, must be called within a tokio runtime
+    pub async fn start() -> MockServer {
+        MockServer::start_with_client(WeedClient::new()).await
+    }
+
+    /// Starts the server, masters, volumes and filers handed out use the given client
+    pub async fn start_with_client(client: WeedClient) -> MockServer {
+        let state = Arc::new(Mutex::new(State {
+            next_key: 1,
+            ..Default::default()
+        }));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        {
+            let mut state = state_lock(&state);
+            state.url = addr.to_string();
+            state.locations = vec![addr.to_string()];
+        }
+
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+
+        MockServer {
+            addr,
+            client,
+            state,
+            shutdown: Some(tx),
+        }
+    }
+
+    pub fn host(&self) -> String {
+        self.addr.ip().to_string()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// A master pointing to the server
+    pub fn master(&self) -> Master {
+        self.client.master(&self.host(), Some(self.port()))
+    }
+
+    /// A volume pointing to the server
+    pub fn volume(&self) -> Volume {
+        self.client.volume(&self.host(), Some(self.port()))
+    }
+
+    /// A filer pointing to the server
+    pub fn filer(&self) -> Filer {
+        self.client.filer(&self.host(), Some(self.port()))
+    }
+
+    /// Replaces the faults for the following requests
+    pub fn inject(&self, faults: Faults) {
+        state_lock(&self.state).faults = faults;
+    }
+
+    /// Removes all faults
+    pub fn heal(&self) {
+        self.inject(Faults::default());
+    }
+
+    /// Number of requests received so far, including failed ones
+    pub fn requests(&self) -> usize {
+        state_lock(&self.state).requests.len()
+    }
+
+    /// Number of requests received so far with a path starting with the prefix, like /dir/assign
+    pub fn requests_to(&self, path_prefix: &str) -> usize {
+        state_lock(&self.state)
+            .requests
+            .iter()
+            .filter(|path| path.starts_with(path_prefix))
+            .count()
+    }
+
+    /// Whether the volume server holds the file id
+    pub fn contains(&self, fid: &FID) -> bool {
+        state_lock(&self.state).volume.contains_key(&fid.to_string())
+    }
+
+    /// Answers /cluster/status as a follower of the given leader address
+    pub fn follow(&self, leader: &str) {
+        state_lock(&self.state).leader = Some(leader.to_string());
+    }
+
+    /// Replaces the addresses handed out as locations of the volume,
+    /// like a replica that is down in front of the server itself
+    pub fn set_locations(&self, urls: &[String]) {
+        state_lock(&self.state).locations = urls.to_vec();
+    }
+
+    /// Moves all files of the volume to another server, assigns and lookups
+    /// answer with the address of the other server and file requests with
+    /// volume not found from then on
+    pub fn move_volume(&self, to: &MockServer) {
+        let files = {
+            let mut state = state_lock(&self.state);
+            state.locations = vec![to.addr.to_string()];
+            state.moved = true;
+            std::mem::take(&mut state.volume)
+        };
+
+        state_lock(&to.state).volume.extend(files);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+fn state_lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What the faults decided for a request
+enum Fault {
+    None,
+    ServerError,
+    Drop,
+    Malformed,
+}
+
+async fn handle(state: Arc<Mutex<State>>, req: Request<Body>) -> Result<Response<Body>, io::Error> {
+    let (latency, fault) = next_fault(&state, req.uri().path());
+
+    if let Some(latency) = latency {
+        tokio::time::sleep(latency).await;
+    }
+
+    match fault {
+        Fault::Drop => Err(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "injected dropped connection",
+        )),
+        Fault::ServerError => Ok(json_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": "injected server error" }),
+        )),
+        Fault::Malformed => Ok(Response::new(Body::from("{ injected malformed body"))),
+        Fault::None => Ok(route(&state, req).await),
+    }
+}
+
+fn next_fault(state: &Mutex<State>, path: &str) -> (Option<Duration>, Fault) {
+    let mut state = state_lock(state);
+    state.requests.push(path.to_string());
+
+    let faults = &mut state.faults;
+    let affected = match &faults.path_prefix {
+        Some(prefix) => path.starts_with(prefix.as_str()),
+        None => true,
+    };
+
+    if !affected {
+        return (None, Fault::None);
+    }
+
+    let fault = if faults.dropped_connections > 0 {
+        faults.dropped_connections -= 1;
+        Fault::Drop
+    } else if faults.server_errors > 0 {
+        faults.server_errors -= 1;
+        Fault::ServerError
+    } else if faults.malformed_bodies > 0 {
+        faults.malformed_bodies -= 1;
+        Fault::Malformed
+    } else {
+        Fault::None
+    };
+
+    (faults.latency, fault)
+}
+
+async fn route(state: &Mutex<State>, req: Request<Body>) -> Response<Body> {
+    let path = req.uri().path().to_string();
+    let query = parse_query(req.uri().query().unwrap_or_default());
+
+    match (req.method(), path.as_str()) {
+        (_, "/dir/assign") => assign(state, &query),
+        (_, "/dir/lookup") => lookup(state, &query),
+        (_, "/cluster/status") => {
+            let state = state_lock(state);
+            let leader = state.leader.as_ref().unwrap_or(&state.url);
+            json_response(
+                StatusCode::OK,
+                json!({
+                    "IsLeader": state.leader.is_none(),
+                    "Leader": leader,
+                    "MaxVolumeId": VOLUME_ID,
+                }),
+            )
+        }
+        (_, "/cluster/healthz") => Response::new(Body::empty()),
+        (_, "/dir/status") => dir_status(state),
+        (_, "/vol/status") => volume_status(state),
+        (_, "/vol/grow") => grow(state, &query),
+        (_, "/vol/vacuum") => vacuum(state, &query),
+        (_, "/col/delete") => delete_collection(state, &query),
+        (_, "/status") => status(state),
+        (_, "/delete") => batch_delete(state, req).await,
+        _ => match FID::from_string(path.trim_start_matches('/')) {
+            Ok(fid) => file(state, fid, req).await,
+            Err(_) => filer(state, path, req).await,
+        },
+    }
+}
+
+fn assign(state: &Mutex<State>, query: &HashMap<String, String>) -> Response<Body> {
+    let count: u64 = query
+        .get("count")
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let mut state = state_lock(state);
+    let key = state.next_key;
+    state.next_key += count;
+    if let Some(collection) = query.get("collection") {
+        state.collections.insert(collection.clone());
+    }
+
+    // key followed by an 8 digit cookie like SeaweedFS
+    let fid = format!("{},{:x}{:08x}", VOLUME_ID, key, key.wrapping_mul(2654435761) as u32);
+
+    let url = &state.locations[0];
+    json_response(
+        StatusCode::OK,
+        json!({ "fid": fid, "url": url, "publicUrl": url, "count": count }),
+    )
+}
+
+/// Grows volumes of a single data node, so only replication 000 can be grown
+fn grow(state: &Mutex<State>, query: &HashMap<String, String>) -> Response<Body> {
+    let count: u32 = query.get("count").and_then(|c| c.parse().ok()).unwrap_or(1);
+    let replication = query
+        .get("replication")
+        .map(String::as_str)
+        .unwrap_or("000");
+
+    if count == 0 {
+        return json_response(
+            StatusCode::NOT_ACCEPTABLE,
+            json!({ "error": "count should be a positive number" }),
+        );
+    }
+    if replication != "000" {
+        return json_response(
+            StatusCode::NOT_ACCEPTABLE,
+            json!({ "error": format!("cannot grow volume group with replication {}: not enough data nodes", replication) }),
+        );
+    }
+
+    if let Some(collection) = query.get("collection") {
+        state_lock(state).collections.insert(collection.clone());
+    }
+    json_response(StatusCode::OK, json!({ "count": count }))
+}
+
+/// Nothing is ever deleted on disk, so vacuuming only checks the threshold and reports the topology
+fn vacuum(state: &Mutex<State>, query: &HashMap<String, String>) -> Response<Body> {
+    let valid = query
+        .get("garbageThreshold")
+        .map(|t| matches!(t.parse::<f32>(), Ok(t) if (0.0..=1.0).contains(&t)))
+        .unwrap_or(true);
+
+    match valid {
+        true => dir_status(state),
+        false => json_response(
+            StatusCode::NOT_ACCEPTABLE,
+            json!({ "error": "garbageThreshold should be between 0.0 and 1.0" }),
+        ),
+    }
+}
+
+fn delete_collection(state: &Mutex<State>, query: &HashMap<String, String>) -> Response<Body> {
+    let collection = query.get("collection").cloned().unwrap_or_default();
+
+    match state_lock(state).collections.remove(&collection) {
+        true => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+        false => json_response(
+            StatusCode::BAD_REQUEST,
+            json!({ "error": format!("collection {} does not exist", collection) }),
+        ),
+    }
+}
+
+fn lookup(state: &Mutex<State>, query: &HashMap<String, String>) -> Response<Body> {
+    let volume_id = query.get("volumeId").cloned().unwrap_or_default();
+    let id = volume_id.split(',').next().unwrap_or_default();
+
+    if id != VOLUME_ID.to_string() {
+        return json_response(
+            StatusCode::NOT_FOUND,
+            json!({ "volumeOrFileId": volume_id, "error": format!("volume id {} not found", id) }),
+        );
+    }
+
+    let locations: Vec<Value> = state_lock(state)
+        .locations
+        .iter()
+        .map(|url| json!({ "url": url, "publicUrl": url }))
+        .collect();
+    json_response(
+        StatusCode::OK,
+        json!({ "volumeOrFileId": volume_id, "locations": locations }),
+    )
+}
+
+fn volume_info(state: &State) -> Value {
+    let size: usize = state.volume.values().map(|f| f.data.len()).sum();
+
+    json!({
+        "Id": VOLUME_ID,
+        "Size": size,
+        "FileCount": state.volume.len(),
+        "ReplicaPlacement": {},
+        "Ttl": { "Count": 0, "Unit": 0 },
+        "Version": 3,
+        "ReadOnly": false,
+    })
+}
+
+fn dir_status(state: &Mutex<State>) -> Response<Body> {
+    let state = state_lock(state);
+    let node = json!({
+        "Url": state.url,
+        "PublicUrl": state.url,
+        "Max": 8,
+        "Free": 7,
+        "Volumes": 1,
+        "VolumeIds": " 1",
+        "EcShards": 0,
+    });
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "Version": "mock",
+            "Topology": {
+                "Max": 8,
+                "Free": 7,
+                "DataCenters": [{
+                    "Id": "DefaultDataCenter",
+                    "Max": 8,
+                    "Free": 7,
+                    "Racks": [{ "Id": "DefaultRack", "Max": 8, "Free": 7, "DataNodes": [node] }],
+                }],
+                "layouts": [{ "collection": "", "replication": "000", "ttl": "", "writables": [VOLUME_ID] }],
+            },
+        }),
+    )
+}
+
+fn volume_status(state: &Mutex<State>) -> Response<Body> {
+    let state = state_lock(state);
+    let mut nodes = serde_json::Map::new();
+    nodes.insert(state.url.clone(), json!([volume_info(&state)]));
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "Version": "mock",
+            "Volumes": {
+                "Max": 8,
+                "Free": 7,
+                "DataCenters": { "DefaultDataCenter": { "DefaultRack": nodes } },
+            },
+        }),
+    )
+}
+
+fn status(state: &Mutex<State>) -> Response<Body> {
+    let state = state_lock(state);
+    let volumes = match state.moved {
+        true => vec![],
+        false => vec![volume_info(&state)],
+    };
+
+    json_response(
+        StatusCode::OK,
+        json!({ "Version": "mock", "Volumes": volumes, "DiskStatuses": [] }),
+    )
+}
+
+/// Upload, download, head and delete of a file id on the volume server
+async fn file(state: &Mutex<State>, fid: FID, req: Request<Body>) -> Response<Body> {
+    if fid.volume_id != VOLUME_ID || state_lock(state).moved {
+        return json_response(
+            StatusCode::NOT_FOUND,
+            json!({ "error": format!("volume {} not found", fid.volume_id) }),
+        );
+    }
+
+    let key = fid.to_string();
+    let query = parse_query(req.uri().query().unwrap_or_default());
+    let cm = query.get("cm").map(|cm| cm == "true");
+
+    match *req.method() {
+        Method::PUT | Method::POST => {
+            let mut stored = match read_upload(req).await {
+                Ok(stored) => stored,
+                Err(resp) => return resp,
+            };
+            stored.manifest = cm == Some(true);
+            let resp = json!({
+                "name": stored.file_name.clone().unwrap_or_default(),
+                "size": stored.data.len(),
+                "eTag": stored.e_tag,
+            });
+
+            state_lock(state).volume.insert(key, stored);
+            json_response(StatusCode::CREATED, resp)
+        }
+        Method::DELETE => {
+            let mut state = state_lock(state);
+            match state.volume.remove(&key) {
+                Some(stored) => {
+                    // like SeaweedFS the chunks of a manifest go with it
+                    if stored.manifest {
+                        for chunk in manifest_chunks(&stored) {
+                            state.volume.remove(&chunk);
+                        }
+                    }
+                    json_response(StatusCode::ACCEPTED, json!({ "size": stored.data.len() }))
+                }
+                None => json_response(StatusCode::NOT_FOUND, json!({ "size": 0 })),
+            }
+        }
+        Method::GET | Method::HEAD => {
+            let stored = state_lock(state).volume.get(&key).cloned();
+            match stored {
+                Some(stored) if stored.manifest && cm != Some(false) => {
+                    match join_chunks(state, &stored) {
+                        Some(joined) => download(&req, &joined),
+                        None => json_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            json!({ "error": "chunk of the manifest not found" }),
+                        ),
+                    }
+                }
+                Some(stored) => download(&req, &stored),
+                None => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())
+                    .unwrap_or_default(),
+            }
+        }
+        _ => json_response(StatusCode::METHOD_NOT_ALLOWED, json!({ "error": "method not allowed" })),
+    }
+}
+
+/// File ids of the chunks listed in a manifest
+fn manifest_chunks(stored: &StoredFile) -> Vec<String> {
+    serde_json::from_slice::<ChunkManifest>(&stored.data)
+        .map(|manifest| manifest.chunks.iter().map(|c| c.fid.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The manifest with the chunks joined as its data, none if a chunk is missing
+fn join_chunks(state: &Mutex<State>, stored: &StoredFile) -> Option<StoredFile> {
+    let state = state_lock(state);
+    let mut data = Vec::new();
+
+    for chunk in manifest_chunks(stored) {
+        data.extend_from_slice(&state.volume.get(&chunk)?.data);
+    }
+
+    Some(StoredFile {
+        data: Bytes::from(data),
+        ..stored.clone()
+    })
+}
+
+/// Deletes the fid parameters of the query and the form body
+async fn batch_delete(state: &Mutex<State>, req: Request<Body>) -> Response<Body> {
+    let query = req.uri().query().unwrap_or_default().to_string();
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+    let form = format!("{}&{}", query, String::from_utf8_lossy(&body));
+
+    let mut state = state_lock(state);
+    let results: Vec<Value> = parse_pairs(&form)
+        .into_iter()
+        .filter(|(name, _)| name == "fid")
+        .map(|(_, fid)| match FID::from_string(&fid) {
+            Err(err) => json!({ "fid": fid, "status": 400, "size": 0, "error": err.to_string() }),
+            Ok(parsed) if parsed.volume_id != VOLUME_ID || state.moved => {
+                json!({ "fid": fid, "status": 404, "size": 0, "error": "volume not found" })
+            }
+            Ok(parsed) => match state.volume.remove(&parsed.to_string()) {
+                Some(stored) => json!({ "fid": fid, "status": 202, "size": stored.data.len() }),
+                None => json!({ "fid": fid, "status": 404, "size": 0 }),
+            },
+        })
+        .collect();
+
+    json_response(StatusCode::ACCEPTED, Value::Array(results))
+}
+
+/// Files and directories on the filer, times and chunks are not modelled
+async fn filer(state: &Mutex<State>, path: String, req: Request<Body>) -> Response<Body> {
+    let path = percent_decode(&path);
+    let query = parse_query(req.uri().query().unwrap_or_default());
+    let multipart = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+
+    match *req.method() {
+        Method::POST if query.contains_key("mv.from") => {
+            filer_move(state, &query["mv.from"], &path)
+        }
+        Method::POST if path.ends_with('/') && !multipart => {
+            let mut state = state_lock(state);
+            let mut dir = dir_key(&path);
+            while !dir.is_empty() {
+                let parent = dir
+                    .rsplit_once('/')
+                    .map(|(p, _)| p.to_string())
+                    .unwrap_or_default();
+                state.dirs.insert(std::mem::replace(&mut dir, parent));
+            }
+            json_response(StatusCode::CREATED, json!({}))
+        }
+        Method::PUT | Method::POST => {
+            let mut stored = match read_upload(req).await {
+                Ok(stored) => stored,
+                Err(resp) => return resp,
+            };
+
+            let mut path = path;
+            if path.ends_with('/') {
+                path.push_str(stored.file_name.as_deref().unwrap_or("file"));
+            }
+            let name = path.rsplit('/').next().unwrap_or_default().to_string();
+            stored.file_name.get_or_insert(name.clone());
+
+            let size = stored.data.len();
+            state_lock(state).filer.insert(path, stored);
+            json_response(StatusCode::CREATED, json!({ "name": name, "size": size }))
+        }
+        Method::DELETE => filer_delete(state, &path, query.get("recursive").map(|r| r == "true")),
+        Method::GET if path.ends_with('/') => list(state, &path, &query),
+        Method::GET if query.contains_key("metadata") => {
+            let state = state_lock(state);
+            match entry(&state, &path) {
+                Some(entry) => json_response(StatusCode::OK, entry),
+                None => json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+            }
+        }
+        Method::GET | Method::HEAD => {
+            let stored = state_lock(state).filer.get(&path).cloned();
+            match stored {
+                Some(stored) => download(&req, &stored),
+                None => json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+            }
+        }
+        _ => json_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            json!({ "error": "method not allowed" }),
+        ),
+    }
+}
+
+/// Directory path without trailing slash, the root is empty
+fn dir_key(path: &str) -> String {
+    path.trim_end_matches('/').to_string()
+}
+
+fn is_dir(state: &State, dir: &str) -> bool {
+    let prefix = format!("{}/", dir);
+    dir.is_empty()
+        || state.dirs.contains(dir)
+        || state.dirs.iter().any(|d| d.starts_with(&prefix))
+        || state.filer.keys().any(|f| f.starts_with(&prefix))
+}
+
+/// Filer entry JSON of a file or directory
+fn entry(state: &State, path: &str) -> Option<Value> {
+    let (full_path, mode, size, mime) = match state.filer.get(path) {
+        Some(stored) => (
+            path.to_string(),
+            0o660,
+            stored.data.len(),
+            stored.content_type.clone().unwrap_or_default(),
+        ),
+        None if is_dir(state, &dir_key(path)) => {
+            (dir_key(path), (1u32 << 31) | 0o770, 0, String::new())
+        }
+        None => return None,
+    };
+
+    Some(json!({
+        "FullPath": full_path,
+        "Mtime": "1970-01-01T00:00:00Z",
+        "Crtime": "1970-01-01T00:00:00Z",
+        "Mode": mode,
+        "Uid": 0,
+        "Gid": 0,
+        "Mime": mime,
+        "FileSize": size,
+    }))
+}
+
+/// Direct children of a directory sorted by name, paged by limit and lastFileName
+fn list(state: &Mutex<State>, path: &str, query: &HashMap<String, String>) -> Response<Body> {
+    let state = state_lock(state);
+    let dir = dir_key(path);
+
+    if !is_dir(&state, &dir) {
+        return json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }));
+    }
+
+    let prefix = format!("{}/", dir);
+    let children: BTreeMap<&str, String> = state
+        .filer
+        .keys()
+        .chain(state.dirs.iter())
+        .filter_map(|p| p.strip_prefix(&prefix))
+        .filter_map(|rest| rest.split('/').next())
+        .filter(|name| !name.is_empty())
+        .map(|name| (name, format!("{}{}", prefix, name)))
+        .collect();
+
+    let limit: usize = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(100);
+    let last = query.get("lastFileName").cloned().unwrap_or_default();
+    let mut remaining = children
+        .iter()
+        .filter(|(name, _)| last.is_empty() || **name > last.as_str());
+
+    let page: Vec<(&str, &String)> = remaining
+        .by_ref()
+        .take(limit)
+        .map(|(n, p)| (*n, p))
+        .collect();
+    let entries: Vec<Value> = page.iter().filter_map(|(_, p)| entry(&state, p)).collect();
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "Path": dir,
+            "Entries": entries,
+            "Limit": limit,
+            "LastFileName": page.last().map(|(n, _)| *n).unwrap_or_default(),
+            "ShouldDisplayLoadMore": remaining.next().is_some(),
+            "EmptyFolder": children.is_empty(),
+        }),
+    )
+}
+
+/// Renames a file or a directory with everything below it
+fn filer_move(state: &Mutex<State>, from: &str, to: &str) -> Response<Body> {
+    let mut state = state_lock(state);
+    let (from, to) = (dir_key(from), dir_key(to));
+
+    if let Some(stored) = state.filer.remove(&from) {
+        state.filer.insert(to, stored);
+        return json_response(StatusCode::OK, json!({}));
+    }
+
+    if from.is_empty() || !is_dir(&state, &from) {
+        return json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }));
+    }
+
+    let prefix = format!("{}/", from);
+    let moved = |p: &String| p == &from || p.starts_with(&prefix);
+    let rename = |p: String| format!("{}{}", to, &p[from.len()..]);
+
+    let files: Vec<String> = state.filer.keys().filter(|p| moved(p)).cloned().collect();
+    for path in files {
+        if let Some(stored) = state.filer.remove(&path) {
+            state.filer.insert(rename(path), stored);
+        }
+    }
+
+    let dirs: Vec<String> = state.dirs.iter().filter(|p| moved(p)).cloned().collect();
+    for path in dirs {
+        state.dirs.remove(&path);
+        state.dirs.insert(rename(path));
+    }
+    state.dirs.insert(to);
+
+    json_response(StatusCode::OK, json!({}))
+}
+
+/// Deletes a file or a directory, non empty directories only with recursive=true
+fn filer_delete(state: &Mutex<State>, path: &str, recursive: Option<bool>) -> Response<Body> {
+    let mut state = state_lock(state);
+
+    if state.filer.remove(path).is_some() {
+        return Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap_or_default();
+    }
+
+    let dir = dir_key(path);
+    if dir.is_empty() || !is_dir(&state, &dir) {
+        return json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }));
+    }
+
+    let prefix = format!("{}/", dir);
+    let empty = !state
+        .filer
+        .keys()
+        .chain(state.dirs.iter())
+        .any(|p| p.starts_with(&prefix));
+    if !empty && recursive != Some(true) {
+        return json_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": format!("fail to delete non-empty folder: {}", dir) }),
+        );
+    }
+
+    state.filer.retain(|p, _| !p.starts_with(&prefix));
+    state.dirs.retain(|p| p != &dir && !p.starts_with(&prefix));
+
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+/// Reads a raw or multipart/form-data upload with its Seaweed-* pairs
+async fn read_upload(req: Request<Body>) -> Result<StoredFile, Response<Body>> {
+    let (parts, body) = req.into_parts();
+    let header = |name: header::HeaderName| {
+        parts
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+
+    let body = hyper::body::to_bytes(body).await.map_err(|e| {
+        json_response(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() }))
+    })?;
+
+    let mut content_type = header(header::CONTENT_TYPE);
+    let mut file_name = header(header::CONTENT_DISPOSITION).and_then(|v| {
+        v.split(';')
+            .filter_map(|part| part.trim().strip_prefix("filename="))
+            .map(|name| name.trim_matches('"').to_string())
+            .next()
+    });
+
+    let boundary = content_type
+        .as_deref()
+        .filter(|c| c.starts_with("multipart/form-data"))
+        .and_then(|c| c.split("boundary=").nth(1))
+        .map(|b| b.trim_matches('"').to_string());
+
+    let data = match boundary {
+        Some(boundary) => {
+            let part = parse_multipart(&body, &boundary).ok_or_else(|| {
+                json_response(StatusCode::BAD_REQUEST, json!({ "error": "invalid multipart body" }))
+            })?;
+            content_type = part.content_type;
+            file_name = part.file_name.or(file_name);
+            part.data
+        }
+        None => body,
+    };
+
+    let pairs = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("seaweed-"))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+
+    Ok(StoredFile {
+        e_tag: format!("{:08x}", hasher.finish() as u32),
+        data,
+        content_type,
+        file_name,
+        pairs,
+        modified: SystemTime::now(),
+        manifest: false,
+    })
+}
+
+struct MultipartFile {
+    data: Bytes,
+    content_type: Option<String>,
+    file_name: Option<String>,
+}
+
+/// Takes the first part of a multipart/form-data body
+fn parse_multipart(body: &Bytes, boundary: &str) -> Option<MultipartFile> {
+    let delimiter = format!("--{}", boundary);
+    let start = find(body, delimiter.as_bytes(), 0)? + delimiter.len();
+    let headers_end = find(body, b"\r\n\r\n", start)?;
+    let data_end = find(body, format!("\r\n{}", delimiter).as_bytes(), headers_end)?;
+
+    let headers = String::from_utf8_lossy(&body[start..headers_end]);
+    let mut content_type = None;
+    let mut file_name = None;
+
+    for line in headers.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some(header) => header,
+            None => continue,
+        };
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-type" => content_type = Some(value.trim().to_string()),
+            "content-disposition" => {
+                file_name = value
+                    .split(';')
+                    .filter_map(|part| part.trim().strip_prefix("filename="))
+                    .map(|name| name.trim_matches('"').to_string())
+                    .next()
+            }
+            _ => (),
+        }
+    }
+
+    Some(MultipartFile {
+        data: body.slice(headers_end + 4..data_end),
+        content_type,
+        file_name,
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+/// Whether the cached copy of the client is still valid, If-None-Match wins over If-Modified-Since
+fn not_modified(req: &Request<Body>, e_tag: &str, modified: SystemTime) -> bool {
+    let header = |name: header::HeaderName| req.headers().get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(if_none_match) = header(header::IF_NONE_MATCH) {
+        return if_none_match
+            .split(',')
+            .any(|tag| tag.trim() == e_tag || tag.trim() == "*");
+    }
+
+    let since = header(header::IF_MODIFIED_SINCE).and_then(|v| httpdate::parse_http_date(v).ok());
+    let modified = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    match since.and_then(|since| since.duration_since(UNIX_EPOCH).ok()) {
+        Some(since) => modified <= since.as_secs(),
+        None => false,
+    }
+}
+
+/// Answers GET and HEAD with the headers of SeaweedFS, supports Range, If-None-Match
+/// and If-Modified-Since
+fn download(req: &Request<Body>, stored: &StoredFile) -> Response<Body> {
+    let e_tag = format!("\"{}\"", stored.e_tag);
+    let mut builder = Response::builder()
+        .header(header::ETAG, &e_tag)
+        .header(header::LAST_MODIFIED, httpdate::fmt_http_date(stored.modified))
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    if let Some(content_type) = &stored.content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+    if let Some(file_name) = &stored.file_name {
+        builder = builder.header(
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename=\"{}\"", file_name),
+        );
+    }
+    for (name, value) in &stored.pairs {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+
+    if not_modified(req, &e_tag, stored.modified) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap_or_default();
+    }
+
+    let total = stored.data.len() as u64;
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| parse_range(v, total));
+
+    let (status, data) = match range {
+        None => (StatusCode::OK, stored.data.clone()),
+        Some(Some((start, end))) => {
+            builder = builder.header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, total),
+            );
+            let data = stored.data.slice(start as usize..end as usize + 1);
+            (StatusCode::PARTIAL_CONTENT, data)
+        }
+        Some(None) => {
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+                .body(Body::empty())
+                .unwrap_or_default()
+        }
+    };
+
+    let builder = builder
+        .status(status)
+        .header(header::CONTENT_LENGTH, data.len());
+
+    match req.method() == Method::HEAD {
+        true => builder.body(Body::empty()),
+        false => builder.body(Body::from(data)),
+    }
+    .unwrap_or_default()
+}
+
+/// Inclusive start and end of a single range, None if it can not be satisfied
+fn parse_range(value: &str, total: u64) -> Option<(u64, u64)> {
+    let range = value.strip_prefix("bytes=")?;
+    let (start, end) = range.split_once('-')?;
+
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) => ByteRange::Inclusive(start, end),
+        (Ok(start), Err(_)) => ByteRange::From(start),
+        (Err(_), Ok(suffix)) => ByteRange::Suffix(suffix),
+        _ => return None,
+    };
+
+    let (start, end) = match range {
+        ByteRange::Inclusive(start, end) => (start, end.min(total.checked_sub(1)?)),
+        ByteRange::From(start) => (start, total.checked_sub(1)?),
+        ByteRange::Suffix(suffix) => (total.checked_sub(suffix.min(total))?, total.checked_sub(1)?),
+    };
+
+    match start <= end {
+        true => Some((start, end)),
+        false => None,
+    }
+}
+
+/// Decodes %XX escapes of an url path
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    parse_pairs(query).into_iter().collect()
+}
+
+fn parse_pairs(query: &str) -> Vec<(String, String)> {
+    reqwest::Url::parse(&format!("http://localhost/?{}", query))
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use crate::{
+        error::WeedError,
+        retry::RetryPolicy,
+        client::WeedClient,
+        utils::ByteRange,
+        volume::{FileRead, UploadFileOptions},
+    };
+
+    use super::{Faults, MockServer};
+
+    #[tokio::test]
+    async fn ranges_and_pairs() {
+        let server = MockServer::start().await;
+        let assigned = server.master().assign_key(&None).await.unwrap();
+        let volume = server.volume();
+
+        let options = UploadFileOptions {
+            pairs: [("Owner".to_string(), "alice".to_string())].into(),
+            ..Default::default()
+        };
+        volume
+            .upload_file_bytes(&assigned.fid, &Bytes::from("Hello World!"), &Some(options))
+            .await
+            .unwrap();
+
+        let meta = volume.head_file(&assigned.fid, &None).await.unwrap();
+        assert_eq!(Some(12), meta.size);
+        assert_eq!(Some(&"alice".to_string()), meta.pairs.get("owner"));
+
+        match volume
+            .get_file_range(&assigned.fid, ByteRange::Suffix(6), &None)
+            .await
+            .unwrap()
+        {
+            FileRead::Partial { data, range, .. } => {
+                assert_eq!(Bytes::from("World!"), data);
+                assert_eq!(Some(12), range.total);
+            }
+            _ => panic!("expected a partial read"),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_injected_failures() {
+        let client = WeedClient::builder()
+            .retry_policy(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::ZERO,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client).await;
+
+        server.inject(Faults {
+            server_errors: 2,
+            path_prefix: Some("/dir/assign".to_string()),
+            ..Default::default()
+        });
+        assert!(server.master().assign_key(&None).await.is_ok());
+        assert_eq!(3, server.requests());
+
+        server.inject(Faults {
+            server_errors: 3,
+            ..Default::default()
+        });
+        let err = server.master().assign_key(&None).await.unwrap_err();
+        assert_eq!(Some(500), err.status());
+
+        server.inject(Faults {
+            dropped_connections: 1,
+            ..Default::default()
+        });
+        let before = server.requests();
+        let resp = server.master().lookup_volume(&"1,01".parse().unwrap(), &None).await.unwrap();
+        assert_eq!(1, resp.locations.len());
+        assert_eq!(2, server.requests() - before);
+
+        server.heal();
+        let err = server.master().lookup_volume(&"7,01".parse().unwrap(), &None).await.unwrap_err();
+        assert!(err.is_not_found());
+
+        // a complete answer that fails to parse would look the same on every attempt
+        server.inject(Faults {
+            malformed_bodies: 1,
+            ..Default::default()
+        });
+        let before = server.requests();
+        let err = server.master().assign_key(&None).await.unwrap_err();
+        assert!(!err.is_retryable());
+        assert_eq!(1, server.requests() - before);
+    }
+
+    #[tokio::test]
+    async fn injected_latency_times_out() {
+        let client = WeedClient::builder()
+            .timeout(Duration::from_millis(50))
+            .retry_policy(RetryPolicy::exponential(1))
+            .build()
+            .unwrap();
+        let server = MockServer::start_with_client(client).await;
+
+        server.inject(Faults {
+            latency: Some(Duration::from_millis(500)),
+            ..Default::default()
+        });
+        let err = server.master().assign_key(&None).await.unwrap_err();
+        assert!(err.is_retryable());
+    }
+}
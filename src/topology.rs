@@ -0,0 +1,280 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{TTLUnits, TTL};
+
+/// Topology of the cluster as returned by [dir_status](crate::master::Master::dir_status)
+///
+/// The volumes of the data nodes are only filled by [topology](crate::master::Master::topology)
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Topology {
+    #[serde(default)]
+    pub max: i64,
+    #[serde(default)]
+    pub free: i64,
+    #[serde(default)]
+    pub data_centers: Vec<DataCenter>,
+    /// Writable volumes per collection, replication and ttl
+    #[serde(default, rename = "layouts")]
+    pub layouts: Vec<VolumeLayout>,
+}
+
+impl Topology {
+    /// All data nodes of all racks in all data centers
+    pub fn data_nodes(&self) -> impl Iterator<Item = &DataNode> {
+        self.data_centers
+            .iter()
+            .flat_map(|dc| dc.racks.iter())
+            .flat_map(|rack| rack.data_nodes.iter())
+    }
+
+    /// Fills the volumes of the data nodes from the volume status
+    pub fn fill_volumes(&mut self, status: &VolumeStatus) {
+        for dc in &mut self.data_centers {
+            for rack in &mut dc.racks {
+                let nodes = status
+                    .data_centers
+                    .get(&dc.id)
+                    .and_then(|racks| racks.get(&rack.id));
+
+                for node in &mut rack.data_nodes {
+                    if let Some(volumes) = nodes.and_then(|nodes| nodes.get(&node.url)) {
+                        node.volumes = volumes.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataCenter {
+    pub id: String,
+    #[serde(default)]
+    pub max: i64,
+    #[serde(default)]
+    pub free: i64,
+    #[serde(default)]
+    pub racks: Vec<Rack>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct Rack {
+    pub id: String,
+    #[serde(default)]
+    pub max: i64,
+    #[serde(default)]
+    pub free: i64,
+    #[serde(default)]
+    pub data_nodes: Vec<DataNode>,
+}
+
+/// A volume server
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataNode {
+    pub url: String,
+    #[serde(default)]
+    pub public_url: String,
+    /// Number of volume slots
+    #[serde(default)]
+    pub max: i64,
+    /// Number of free volume slots
+    #[serde(default)]
+    pub free: i64,
+    #[serde(default, rename = "Volumes")]
+    pub volume_count: u32,
+    #[serde(default)]
+    pub ec_shards: u32,
+    /// Only filled by [topology](crate::master::Master::topology)
+    #[serde(skip_deserializing)]
+    pub volumes: Vec<VolumeInfo>,
+}
+
+/// Writable volumes of a collection with the same replication and ttl
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct VolumeLayout {
+    #[serde(default)]
+    pub collection: String,
+    #[serde(default)]
+    pub replication: String,
+    #[serde(default)]
+    pub ttl: String,
+    #[serde(default)]
+    pub writables: Option<Vec<u32>>,
+}
+
+/// Volumes of the cluster as returned by [volume_status](crate::master::Master::volume_status)
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct VolumeStatus {
+    #[serde(default)]
+    pub max: i64,
+    #[serde(default)]
+    pub free: i64,
+    /// Volumes keyed by data center, rack and data node url
+    #[serde(default)]
+    pub data_centers: HashMap<String, HashMap<String, HashMap<String, Vec<VolumeInfo>>>>,
+}
+
+/// A single volume on a data node
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct VolumeInfo {
+    pub id: u32,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub replica_placement: Option<ReplicaPlacement>,
+    #[serde(default)]
+    pub ttl: Option<VolumeTtl>,
+    #[serde(default)]
+    pub disk_type: String,
+    #[serde(default)]
+    pub collection: String,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub file_count: u64,
+    #[serde(default)]
+    pub delete_count: u64,
+    #[serde(default)]
+    pub deleted_byte_count: u64,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub compact_revision: u32,
+    /// Last modification in epoch seconds
+    #[serde(default)]
+    pub modified_at_second: i64,
+}
+
+/// Number of replicas in other data centers, on other racks and on the same rack
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReplicaPlacement {
+    #[serde(default, rename = "dc")]
+    pub diff_data_center_count: u8,
+    #[serde(default, rename = "rack")]
+    pub diff_rack_count: u8,
+    #[serde(default, rename = "node")]
+    pub same_rack_count: u8,
+}
+
+impl fmt::Display for ReplicaPlacement {
+    /// Formats the placement like the replication option, 010 for example
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.diff_data_center_count, self.diff_rack_count, self.same_rack_count
+        )
+    }
+}
+
+/// Time to live of a volume as stored by SeaweedFS
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct VolumeTtl {
+    #[serde(default)]
+    pub count: u8,
+    #[serde(default)]
+    pub unit: u8,
+}
+
+impl VolumeTtl {
+    /// The ttl as used for assignments, None if the volume never expires
+    pub fn to_ttl(&self) -> Option<TTL> {
+        let unit = match self.unit {
+            1 => TTLUnits::Minute,
+            2 => TTLUnits::Hour,
+            3 => TTLUnits::Day,
+            4 => TTLUnits::Week,
+            5 => TTLUnits::Month,
+            6 => TTLUnits::Year,
+            _ => return None,
+        };
+
+        match self.count {
+            0 => None,
+            count => Some(TTL {
+                unit,
+                value: count as u32,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Topology, VolumeStatus, VolumeTtl};
+
+    #[test]
+    fn parse_and_fill_topology() {
+        let data = r#"{
+            "DataCenters": [{
+                "Free": 3,
+                "Id": "dc1",
+                "Max": 7,
+                "Racks": [{
+                    "DataNodes": [{
+                        "EcShards": 0,
+                        "Free": 3,
+                        "Max": 7,
+                        "PublicUrl": "localhost:8080",
+                        "Url": "localhost:8080",
+                        "VolumeIds": " 1-4",
+                        "Volumes": 4
+                    }],
+                    "Free": 3,
+                    "Id": "DefaultRack",
+                    "Max": 7
+                }]
+            }],
+            "Free": 3,
+            "Max": 7,
+            "layouts": [{"collection": "", "replication": "000", "ttl": "", "writables": [1, 2, 3, 4]}]
+        }"#;
+        let mut topology = serde_json::from_str::<Topology>(data).unwrap();
+        assert_eq!(4, topology.data_nodes().next().unwrap().volume_count);
+
+        let data = r#"{
+            "DataCenters": {
+                "dc1": {
+                    "DefaultRack": {
+                        "localhost:8080": [{
+                            "Id": 1,
+                            "Size": 1024,
+                            "ReplicaPlacement": {"rack": 1},
+                            "Ttl": {"Count": 3, "Unit": 3},
+                            "Collection": "",
+                            "Version": 3,
+                            "FileCount": 10,
+                            "DeleteCount": 2,
+                            "DeletedByteCount": 128,
+                            "ReadOnly": true
+                        }]
+                    }
+                }
+            },
+            "Free": 3,
+            "Max": 7
+        }"#;
+        let status = serde_json::from_str::<VolumeStatus>(data).unwrap();
+        topology.fill_volumes(&status);
+
+        let volume = &topology.data_nodes().next().unwrap().volumes[0];
+        assert_eq!(1, volume.id);
+        assert!(volume.read_only);
+        assert_eq!("010", volume.replica_placement.as_ref().unwrap().to_string());
+        assert_eq!("3d", volume.ttl.as_ref().unwrap().to_ttl().unwrap().to_string());
+    }
+
+    #[test]
+    fn volume_without_ttl() {
+        assert!(VolumeTtl::default().to_ttl().is_none());
+    }
+}
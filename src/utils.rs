@@ -1,4 +1,7 @@
-use std::{error::Error, fmt};
+// the option types and FID keep their inherent to_string of the first releases
+#![allow(clippy::inherent_to_string)]
+
+use std::{fmt, str::FromStr};
 
 use serde::{
     de::{self, Visitor},
@@ -8,7 +11,7 @@ use thiserror::Error;
 
 /// SeaweedFS only allows a max replication of 2 per type
 /// so we use the enum to implement this limit
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ReplicationValues {
     OneReplica,
     TwoReplicas,
@@ -25,13 +28,36 @@ impl ReplicationValues {
 
 /// Replication factor for volumes
 /// for example 100 means 1 replica in another data center
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ReplicationType {
     data_center: Option<ReplicationValues>,
     other_rack: Option<ReplicationValues>,
     same_rack: Option<ReplicationValues>,
 }
 
+impl ReplicationType {
+    /// Replicas in other data centers, on other racks and on the same rack
+    ///
+    /// # Example
+    /// ```
+    /// use rusty_weed::utils::{ReplicationType, ReplicationValues};
+    ///
+    /// let replication = ReplicationType::new(None, Some(ReplicationValues::OneReplica), None);
+    /// assert_eq!("010", replication.to_string());
+    /// ```
+    pub fn new(
+        data_center: Option<ReplicationValues>,
+        other_rack: Option<ReplicationValues>,
+        same_rack: Option<ReplicationValues>,
+    ) -> ReplicationType {
+        ReplicationType {
+            data_center,
+            other_rack,
+            same_rack,
+        }
+    }
+}
+
 impl ReplicationType {
     pub fn to_string(&self) -> String {
         let mut s = String::new();
@@ -55,6 +81,27 @@ impl ReplicationType {
     }
 }
 
+impl FromStr for ReplicationType {
+    type Err = ParseOptionErrors;
+
+    /// Parses three digits like 010
+    fn from_str(s: &str) -> Result<ReplicationType, ParseOptionErrors> {
+        let invalid = || ParseOptionErrors::InvalidReplication(s.to_string());
+        let digit = |c: char| match c {
+            '0' => Ok(None),
+            '1' => Ok(Some(ReplicationValues::OneReplica)),
+            '2' => Ok(Some(ReplicationValues::TwoReplicas)),
+            _ => Err(invalid()),
+        };
+
+        let digits: Vec<char> = s.chars().collect();
+        match digits[..] {
+            [dc, rack, node] => Ok(ReplicationType::new(digit(dc)?, digit(rack)?, digit(node)?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
 impl Serialize for ReplicationType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -65,7 +112,7 @@ impl Serialize for ReplicationType {
 }
 
 /// Units for TTL for requesting a file key
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TTLUnits {
     Minute,
     Hour,
@@ -89,7 +136,7 @@ impl TTLUnits {
 }
 
 /// Time to live option struct for assigning a file id
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TTL {
     pub unit: TTLUnits,
     pub value: u32,
@@ -97,7 +144,33 @@ pub struct TTL {
 
 impl TTL {
     pub fn to_string(&self) -> String {
-        concat_string!(self.unit.to_string(), self.value.to_string())
+        concat_string!(self.value.to_string(), self.unit.to_string())
+    }
+}
+
+impl FromStr for TTL {
+    type Err = ParseOptionErrors;
+
+    /// Parses a number followed by the unit like 3d
+    fn from_str(s: &str) -> Result<TTL, ParseOptionErrors> {
+        let invalid = || ParseOptionErrors::InvalidTTL(s.to_string());
+        let split = s.len().checked_sub(1).filter(|i| s.is_char_boundary(*i)).ok_or_else(invalid)?;
+        let (value, unit) = s.split_at(split);
+
+        let unit = match unit {
+            "m" => TTLUnits::Minute,
+            "h" => TTLUnits::Hour,
+            "d" => TTLUnits::Day,
+            "w" => TTLUnits::Week,
+            "M" => TTLUnits::Month,
+            "y" => TTLUnits::Year,
+            _ => return Err(invalid()),
+        };
+
+        Ok(TTL {
+            unit,
+            value: value.parse().map_err(|_| invalid())?,
+        })
     }
 }
 
@@ -110,19 +183,33 @@ impl Serialize for TTL {
     }
 }
 
-#[derive(Error, Debug)]
-enum FIDErrors {
+/// Errors while parsing a [ReplicationType] or [TTL]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseOptionErrors {
+    #[error("Invalid replication {0}, expected three digits from 0 to 2 like 010")]
+    InvalidReplication(String),
+    #[error("Invalid ttl {0}, expected a number followed by m, h, d, w, M or y like 3d")]
+    InvalidTTL(String),
+}
+
+/// Errors while parsing a [FID]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FIDErrors {
     #[error("Missing formatted volume id")]
     MissingVolumeId,
     #[error("Missing formatted file string")]
     MissingFileString,
+    #[error("Volume id or count is not a number")]
+    InvalidNumber(#[from] std::num::ParseIntError),
 }
 
 /// Representation of a SeaweedFS file id (3,32834855_1 for example)
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FID {
     pub volume_id: u32,
     pub file_string: String,
+    /// Suffix of the further file ids of a multi count assignment,
+    /// see [fids](crate::master::AssignKeyResponse::fids)
     pub count: Option<u64>,
 }
 
@@ -136,7 +223,8 @@ impl FID {
         }
     }
 
-    pub fn from_string(s: &str) -> Result<FID, Box<dyn Error>> {
+    #[allow(clippy::needless_late_init, clippy::single_match)]
+    pub fn from_string(s: &str) -> Result<FID, FIDErrors> {
         let mut parts = s.split(",");
 
         let volume_id: u32;
@@ -145,7 +233,7 @@ impl FID {
 
         match parts.next() {
             Some(s) => volume_id = s.parse::<u32>()?,
-            None => return Err(Box::new(FIDErrors::MissingVolumeId)),
+            None => return Err(FIDErrors::MissingVolumeId),
         }
 
         match parts.next() {
@@ -153,7 +241,7 @@ impl FID {
                 let mut count_parts = s.split("_");
                 match count_parts.next() {
                     Some(s) => file_string = s.to_string(),
-                    None => return Err(Box::new(FIDErrors::MissingFileString)),
+                    None => return Err(FIDErrors::MissingFileString),
                 }
 
                 match count_parts.next() {
@@ -161,7 +249,7 @@ impl FID {
                     None => (),
                 }
             },
-            None => return Err(Box::new(FIDErrors::MissingFileString)),
+            None => return Err(FIDErrors::MissingFileString),
         }
 
         
@@ -173,6 +261,14 @@ impl FID {
     }
 }
 
+impl FromStr for FID {
+    type Err = FIDErrors;
+
+    fn from_str(s: &str) -> Result<FID, FIDErrors> {
+        FID::from_string(s)
+    }
+}
+
 impl<'de> Deserialize<'de> for FID {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -212,18 +308,135 @@ impl Serialize for FID {
     }
 }
 
+/// Byte range of a file used for the http Range header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// From the first to the last byte, both inclusive
+    Inclusive(u64, u64),
+    /// From a byte until the end of the file
+    From(u64),
+    /// The last n bytes of the file
+    Suffix(u64),
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Inclusive(start, end) => write!(f, "bytes={}-{}", start, end),
+            Self::From(start) => write!(f, "bytes={}-", start),
+            Self::Suffix(len) => write!(f, "bytes=-{}", len),
+        }
+    }
+}
+
+/// Parsed Content-Range header of a 206 Partial Content response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    /// First byte, inclusive
+    pub start: u64,
+    /// Last byte, inclusive
+    pub end: u64,
+    /// Size of the whole file if known
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    /// Parses a header like bytes 0-4/12 or bytes 0-4/*
+    pub fn parse(s: &str) -> Option<ContentRange> {
+        let (range, total) = s.strip_prefix("bytes ")?.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+
+        Some(ContentRange {
+            start: start.trim().parse().ok()?,
+            end: end.trim().parse().ok()?,
+            total: match total.trim() {
+                "*" => None,
+                t => Some(t.parse().ok()?),
+            },
+        })
+    }
+}
+
 /// Location strings for volume lookup
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Location {
     pub public_url: String,
     pub url: String,
+    /// Data center of the volume server, only sent by newer masters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_center: Option<String>,
+}
+
+/// Random number without pulling in a rng crate,
+/// every [RandomState](std::collections::hash_map::RandomState) is seeded randomly
+pub(crate) fn random_u64() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    RandomState::new().build_hasher().finish()
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::FID;
+    use crate::utils::{
+        ByteRange, ContentRange, FIDErrors, Location, ParseOptionErrors, ReplicationType, TTLUnits,
+        FID, TTL,
+    };
+
+    #[test]
+    fn ttl_format() {
+        let ttl = TTL {
+            unit: TTLUnits::Day,
+            value: 3,
+        };
+        assert_eq!("3d", ttl.to_string());
+    }
+
+    #[test]
+    fn parse_options() {
+        assert_eq!("12M", "12M".parse::<TTL>().unwrap().to_string());
+        assert_eq!(
+            Err(ParseOptionErrors::InvalidTTL("3x".to_string())),
+            "3x".parse::<TTL>().map(|t| t.to_string())
+        );
+        assert!("d".parse::<TTL>().is_err());
+
+        assert_eq!("021", "021".parse::<ReplicationType>().unwrap().to_string());
+        assert!("03".parse::<ReplicationType>().is_err());
+        assert!("030".parse::<ReplicationType>().is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!("bytes=0-4", ByteRange::Inclusive(0, 4).to_string());
+        assert_eq!("bytes=5-", ByteRange::From(5).to_string());
+        assert_eq!("bytes=-3", ByteRange::Suffix(3).to_string());
+
+        assert_eq!(
+            Some(ContentRange { start: 0, end: 4, total: Some(12) }),
+            ContentRange::parse("bytes 0-4/12")
+        );
+        assert_eq!(
+            Some(ContentRange { start: 5, end: 11, total: None }),
+            ContentRange::parse("bytes 5-11/*")
+        );
+        assert_eq!(None, ContentRange::parse("bytes */12"));
+    }
+
+    #[test]
+    fn parse_location_data_center() {
+        let data = r#"{"url":"1.1.1.1:8080","publicUrl":"1.1.1.1:8080","dataCenter":"dc1"}"#;
+        let location = serde_json::from_str::<Location>(data).unwrap();
+        assert_eq!(Some("dc1".to_string()), location.data_center);
+
+        let data = r#"{"url":"1.1.1.1:8080","publicUrl":"1.1.1.1:8080"}"#;
+        let location = serde_json::from_str::<Location>(data).unwrap();
+        assert_eq!(None, location.data_center);
+    }
 
     #[test]
     fn check_fid_parsing() {
@@ -235,4 +448,10 @@ mod tests {
             _ => panic!("Failed to parse fid")
         }
     }
+
+    #[test]
+    fn fid_parse_errors() {
+        assert_eq!(Err(FIDErrors::MissingFileString), "3".parse::<FID>());
+        assert!(matches!(FID::from_string("x,01"), Err(FIDErrors::InvalidNumber(_))));
+    }
 }
\ No newline at end of file
@@ -1,146 +1,504 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicU32, Ordering},
+    time::SystemTime,
+};
+
 use bytes::Bytes;
-use reqwest::{multipart::Form, Response};
+use reqwest::{
+    header::{self, HeaderMap},
+    multipart::{Form, Part},
+    Body, RequestBuilder, Response,
+};
 use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
 
-use crate::utils::FID;
+use crate::{
+    client::WeedClient,
+    error::{ResponseError, WeedError},
+    stream::FileStream,
+    topology::VolumeInfo,
+    utils::{ByteRange, ContentRange, FID},
+};
 
+/// Address of a volume server and the http client used to talk to it
+///
+/// Created with [new](Volume::new), [with_client](Volume::with_client), [from_str](Volume::from_str)
+/// or by a [WeedClient](crate::client::WeedClient), the client field keeps struct literals out.
+#[derive(Clone, Debug)]
 pub struct Volume {
     pub host: String,
     pub port: Option<u16>,
+    pub(crate) client: WeedClient,
 }
 
 #[derive(Error, Debug)]
 pub enum VolumeErrors {
     #[error("Wrong format of string expected 0.0.0.0:3333 for example")]
     WrongFormat,
-    #[error("Response StatusCode was not CREATED 201 see body for error: {0}")]
-    NotCreated(String),
-    #[error("Response StatusCode was not ACCEPTED 202 see body for error: {0}")]
-    NotAccepted(String),
-    #[error("Response StatusCode was not OK 200 see body for error: {0}")]
-    InvalidRequest(String),
-    #[error("File not found on volume server")]
-    FileNotFound,
+    #[error("File was not created: {0}")]
+    NotCreated(ResponseError),
+    #[error("Deletion was not accepted: {0}")]
+    NotAccepted(ResponseError),
+    #[error("Request was not accepted by the volume server: {0}")]
+    InvalidRequest(ResponseError),
+    #[error("File not found on volume server: {0}")]
+    FileNotFound(ResponseError),
+    #[error("Requested range is not satisfiable: {0}")]
+    RangeNotSatisfiable(ResponseError),
+    #[error("Volume server rejected the JWT: {0}")]
+    Unauthorized(ResponseError),
+    #[error("Volume server failed: {0}")]
+    ServerError(ResponseError),
     #[error("reqwest error")]
     ReqwestError(#[from] reqwest::Error),
     #[error("parsing error")]
     ParseError(#[from] std::num::ParseIntError),
     #[error("serde query string parsing error")]
     SerdeQsError(#[from] serde_qs::Error),
+    #[error("io error")]
+    IoError(#[from] std::io::Error),
 }
 
-impl Volume {
-    pub fn to_string(&self) -> String {
+impl VolumeErrors {
+    /// Maps a failed response to the matching error, other client errors of
+    /// uploads are NotCreated, of deletes NotAccepted and InvalidRequest otherwise
+    pub(crate) async fn from_response(operation: &'static str, resp: Response) -> VolumeErrors {
+        let err = ResponseError::read(operation, resp).await;
+
+        match (err.status, operation) {
+            (404, _) => VolumeErrors::FileNotFound(err),
+            (416, _) => VolumeErrors::RangeNotSatisfiable(err),
+            (401 | 403, _) => VolumeErrors::Unauthorized(err),
+            (500..=599, _) => VolumeErrors::ServerError(err),
+            (_, "upload") => VolumeErrors::NotCreated(err),
+            (_, "delete") => VolumeErrors::NotAccepted(err),
+            _ => VolumeErrors::InvalidRequest(err),
+        }
+    }
+}
+
+impl WeedError for VolumeErrors {
+    fn response(&self) -> Option<&ResponseError> {
+        match self {
+            Self::NotCreated(e)
+            | Self::NotAccepted(e)
+            | Self::InvalidRequest(e)
+            | Self::FileNotFound(e)
+            | Self::RangeNotSatisfiable(e)
+            | Self::Unauthorized(e)
+            | Self::ServerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.port {
-            Some(port) => concat_string!("http://", self.host, ":", port.to_string()),
-            _ => concat_string!("http://", self.host, ":9333"),
+            Some(port) => f.write_str(&concat_string!("http://", self.host, ":", port.to_string())),
+            _ => f.write_str(&concat_string!("http://", self.host, ":9333")),
         }
     }
+}
 
-    /// Creates a master from a string
-    ///
-    /// Should be used in combination with [locations](crate::utils::Location) received from [looking up a volume](crate::master::Master::lookup_volume)
+impl FromStr for Volume {
+    type Err = VolumeErrors;
+
+    /// Parses a volume like 1.1.1.1:8080
     ///
     /// # Example
     /// ```
     /// use rusty_weed::volume::Volume;
     ///
-    /// let master = Volume::from_str("1.1.1.1:9333").unwrap();
+    /// let volume: Volume = "1.1.1.1:8080".parse().unwrap();
     /// ```
-    pub fn from_str(s: &str) -> Result<Volume, VolumeErrors> {
-        let mut parts = s.split(":");
-
-        let host: String;
-        let port: u16;
+    fn from_str(s: &str) -> Result<Volume, VolumeErrors> {
+        let mut parts = s.split(':');
 
-        match parts.next() {
-            Some(s) => host = s.to_string(),
+        let host: String = match parts.next() {
+            Some(s) => s.to_string(),
             None => return Err(VolumeErrors::WrongFormat),
-        }
+        };
 
-        match parts.next() {
-            Some(s) => port = s.parse::<u16>()?,
+        let port: u16 = match parts.next() {
+            Some(s) => s.parse::<u16>()?,
             None => return Err(VolumeErrors::WrongFormat),
+        };
+
+        Ok(Volume::new(&host, Some(port)))
+    }
+}
+
+impl Volume {
+    /// Creates a volume with its own http client
+    ///
+    /// Use [WeedClient::volume](crate::client::WeedClient::volume) to share a connection pool
+    pub fn new(host: &str, port: Option<u16>) -> Volume {
+        Volume::with_client(host, port, WeedClient::new())
+    }
+
+    /// Creates a volume using the given shared client
+    pub fn with_client(host: &str, port: Option<u16>, client: WeedClient) -> Volume {
+        Volume {
+            host: host.to_string(),
+            port,
+            client,
         }
+    }
+
+    /// Shared http client, see [WeedClient](crate::client::WeedClient)
+    pub fn client(&self) -> &WeedClient {
+        &self.client
+    }
 
-        Ok(Volume {
-            host,
-            port: Some(port),
-        })
+    /// Creates a volume from a string
+    ///
+    /// Should be used in combination with [locations](crate::utils::Location) received from [looking up a volume](crate::master::Master::lookup_volume)
+    ///
+    /// # Example
+    /// ```
+    /// use rusty_weed::volume::Volume;
+    ///
+    /// let volume = Volume::from_str("1.1.1.1:8080").unwrap();
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Volume, VolumeErrors> {
+        <Volume as FromStr>::from_str(s)
     }
 
     /// Gets a file from a volume and returns the full reqwest response
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
     pub async fn get_file_response(
         &self,
         fid: &FID,
         options: &Option<GetFileOptions>,
     ) -> Result<Response, VolumeErrors> {
-        let qs_string = serde_qs::to_string(options)?;
+        let url = concat_string!(
+            self.to_string(),
+            "/",
+            fid.to_string(),
+            "?",
+            serde_qs::to_string(options)?
+        );
 
-        let client = reqwest::Client::builder().gzip(true).build()?;
+        self.client
+            .retry()
+            .run(|| async {
+                let req = bearer(self.client.http().get(&url), options).send().await?;
 
-        let req = client
-            .get(concat_string!(
-                self.to_string(),
-                "/",
-                fid.to_string(),
-                "?",
-                qs_string
-            ))
-            .send()
-            .await?;
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req),
+                    _ => Err(VolumeErrors::from_response("download", req).await),
+                }
+            })
+            .await
+    }
 
-        match req.status() {
-            reqwest::StatusCode::OK => Ok(req),
-            _ => Err(VolumeErrors::InvalidRequest(req.text().await?)),
-        }
+    /// Gets a file as a stream of bytes without buffering it in memory
+    ///
+    /// Only the request is retried, errors while streaming the body are passed on
+    pub async fn get_file_stream(
+        &self,
+        fid: &FID,
+        options: &Option<GetFileOptions>,
+    ) -> Result<FileStream, VolumeErrors> {
+        let req = self.get_file_response(fid, options).await?;
+
+        Ok(FileStream::new(req))
     }
 
     /// Gets a file and returns it in bytes
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
     pub async fn get_file_bytes(
         &self,
         fid: &FID,
         options: &Option<GetFileOptions>,
     ) -> Result<Bytes, VolumeErrors> {
-        let qs_string = serde_qs::to_string(options)?;
+        let url = concat_string!(
+            self.to_string(),
+            "/",
+            fid.to_string(),
+            "?",
+            serde_qs::to_string(options)?
+        );
 
-        let client = reqwest::Client::builder().gzip(true).build()?;
+        self.client
+            .retry()
+            .run(|| async {
+                let req = bearer(self.client.http().get(&url), options).send().await?;
 
-        let req = client
-            .get(concat_string!(
-                self.to_string(),
-                "/",
-                fid.to_string(),
-                "?",
-                qs_string
-            ))
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(req.bytes().await?),
+                    _ => Err(VolumeErrors::from_response("download", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Gets a byte range of a file
+    pub async fn get_file_range(
+        &self,
+        fid: &FID,
+        range: ByteRange,
+        options: &Option<GetFileOptions>,
+    ) -> Result<FileRead, VolumeErrors> {
+        let read = PartialReadOptions {
+            range: Some(range),
+            ..Default::default()
+        };
+
+        self.get_file_partial(fid, &read, options).await
+    }
+
+    /// Gets a file or a range of it, optionally only if it changed
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use rusty_weed::{
+    ///     utils::{ByteRange, FID},
+    ///     volume::{FileRead, PartialReadOptions, Volume},
+    /// };
+    ///
+    /// let volume = Volume::new("localhost", Some(8080));
+    /// let fid = FID::from_string("3,01637037d6")?;
+    ///
+    /// let read = PartialReadOptions {
+    ///     range: Some(ByteRange::From(1024)),
+    ///     if_none_match: Some("\"ed076287\"".to_string()),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// match volume.get_file_partial(&fid, &read, &None).await? {
+    ///     FileRead::Full { data, .. } => println!("changed, {} bytes", data.len()),
+    ///     FileRead::Partial { data, range, .. } => println!("bytes {}-{}", range.start, range.end),
+    ///     FileRead::NotModified => println!("still fresh"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_file_partial(
+        &self,
+        fid: &FID,
+        read: &PartialReadOptions,
+        options: &Option<GetFileOptions>,
+    ) -> Result<FileRead, VolumeErrors> {
+        let url = concat_string!(
+            self.to_string(),
+            "/",
+            fid.to_string(),
+            "?",
+            serde_qs::to_string(options)?
+        );
+
+        self.client
+            .retry()
+            .run(|| async {
+                let mut builder = bearer(self.client.http().get(&url), options);
+
+                if let Some(range) = &read.range {
+                    builder = builder.header(header::RANGE, range.to_string());
+                }
+                if let Some(e_tag) = &read.if_none_match {
+                    builder = builder.header(header::IF_NONE_MATCH, quote_e_tag(e_tag));
+                }
+                if let Some(time) = read.if_modified_since {
+                    builder = builder.header(header::IF_MODIFIED_SINCE, httpdate::fmt_http_date(time));
+                }
+
+                let req = builder.send().await?;
+                let FileMeta { e_tag, pairs, .. } = FileMeta::from_headers(req.headers());
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(FileRead::Full {
+                        data: req.bytes().await?,
+                        e_tag,
+                        pairs,
+                    }),
+                    reqwest::StatusCode::PARTIAL_CONTENT => {
+                        let range = req
+                            .headers()
+                            .get(header::CONTENT_RANGE)
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(ContentRange::parse);
+
+                        match range {
+                            Some(range) => Ok(FileRead::Partial {
+                                data: req.bytes().await?,
+                                range,
+                                e_tag,
+                                pairs,
+                            }),
+                            None => Err(VolumeErrors::InvalidRequest(ResponseError {
+                                operation: "download",
+                                url: req.url().to_string(),
+                                status: req.status().as_u16(),
+                                message: "missing or invalid Content-Range header".to_string(),
+                            })),
+                        }
+                    }
+                    reqwest::StatusCode::NOT_MODIFIED => Ok(FileRead::NotModified),
+                    _ => Err(VolumeErrors::from_response("download", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Gets the metadata of a file with a HEAD request without downloading it
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client
+    pub async fn head_file(
+        &self,
+        fid: &FID,
+        options: &Option<GetFileOptions>,
+    ) -> Result<FileMeta, VolumeErrors> {
+        let url = concat_string!(
+            self.to_string(),
+            "/",
+            fid.to_string(),
+            "?",
+            serde_qs::to_string(options)?
+        );
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = bearer(self.client.http().head(&url), options).send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK => Ok(FileMeta::from_headers(req.headers())),
+                    _ => Err(VolumeErrors::from_response("head", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Volumes, erasure coded volumes and disks of the volume server
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run() -> Result<(), rusty_weed::volume::VolumeErrors> {
+    /// use rusty_weed::volume::Volume;
+    ///
+    /// let volume = Volume::new("localhost", Some(8080));
+    /// let status = volume.status().await?;
+    ///
+    /// for v in status.volumes.unwrap_or_default().iter().filter(|v| v.read_only) {
+    ///     println!("volume {} is read only", v.id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn status(&self) -> Result<VolumeServerStatus, VolumeErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/status"))
             .send()
             .await?;
 
         match req.status() {
-            reqwest::StatusCode::OK => Ok(req.bytes().await?),
-            reqwest::StatusCode::NOT_FOUND => Err(VolumeErrors::FileNotFound),
-            _ => Err(VolumeErrors::InvalidRequest(req.text().await?)),
+            reqwest::StatusCode::OK => Ok(req.json::<VolumeServerStatus>().await?),
+            _ => Err(VolumeErrors::from_response("status", req).await),
         }
     }
 
-    /// Deletes a file
-    pub async fn delete_file(&self, fid: &FID) -> Result<DeleteResponse, VolumeErrors> {
-        let client = reqwest::Client::builder().build()?;
-
-        let req = client
-            .delete(concat_string!(self.to_string(), "/", fid.to_string()))
+    /// Usage of the disks of the volume server
+    ///
+    /// The volume server only answers /stats/disk for whitelisted clients
+    pub async fn disk_stats(&self) -> Result<DiskStatsResponse, VolumeErrors> {
+        let req = self
+            .client
+            .http()
+            .get(concat_string!(self.to_string(), "/stats/disk"))
             .send()
             .await?;
 
         match req.status() {
-            reqwest::StatusCode::ACCEPTED => Ok(req.json::<DeleteResponse>().await?),
-            _ => Err(VolumeErrors::NotAccepted(req.text().await?)),
+            reqwest::StatusCode::OK => Ok(req.json::<DiskStatsResponse>().await?),
+            _ => Err(VolumeErrors::from_response("disk stats", req).await),
         }
     }
 
+    /// Deletes a file
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client,
+    /// a retry that finds the file already gone counts as deleted
+    pub async fn delete_file(&self, fid: &FID) -> Result<DeleteResponse, VolumeErrors> {
+        self.delete_file_with(fid, &None).await
+    }
+
+    /// Deletes a file like [delete_file](Volume::delete_file) sending the JWT of the options
+    pub async fn delete_file_with(
+        &self,
+        fid: &FID,
+        options: &Option<DeleteFileOptions>,
+    ) -> Result<DeleteResponse, VolumeErrors> {
+        let url = concat_string!(self.to_string(), "/", fid.to_string());
+        let attempts = AtomicU32::new(0);
+
+        self.client
+            .retry()
+            .run(|| async {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+                let mut builder = self.client.http().delete(&url);
+
+                if let Some(jwt) = options.as_ref().and_then(|o| o.jwt.as_ref()) {
+                    builder = builder.bearer_auth(jwt);
+                }
+
+                let req = builder.send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::ACCEPTED => Ok(req.json::<DeleteResponse>().await?),
+                    reqwest::StatusCode::NOT_FOUND if attempt > 1 => Ok(Default::default()),
+                    _ => Err(VolumeErrors::from_response("delete", req).await),
+                }
+            })
+            .await
+    }
+
+    /// Deletes many files of this volume server with a single request to /delete
+    ///
+    /// Returns a result per file id, deleting a missing file is reported as 404 instead of an error.
+    /// The endpoint is subject to the white list of the volume server and sends no JWT.
+    /// A file lives on every replica of its volume, see [delete_many](crate::store::Store::delete_many).
+    ///
+    /// Retried according to the [retry policy](crate::client::WeedClient::retry) of the client,
+    /// files deleted by a failed attempt are reported as 404 by the retry
+    pub async fn delete_many(&self, fids: &[FID]) -> Result<Vec<DeleteResult>, VolumeErrors> {
+        let url = concat_string!(self.to_string(), "/delete");
+        let form: Vec<(&str, String)> = fids.iter().map(|fid| ("fid", fid.to_string())).collect();
+
+        self.client
+            .retry()
+            .run(|| async {
+                let req = self.client.http().post(&url).form(&form).send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::OK | reqwest::StatusCode::ACCEPTED => {
+                        Ok(req.json::<Vec<DeleteResult>>().await?)
+                    }
+                    _ => Err(VolumeErrors::from_response("delete", req).await),
+                }
+            })
+            .await
+    }
+
     /// Uploads a reqwest form
+    ///
+    /// The form is consumed by the request so this is never retried,
+    /// use [upload_file_bytes](Volume::upload_file_bytes) for retries
     pub async fn upload_file_form(
         &self,
         fid: &FID,
@@ -149,9 +507,9 @@ impl Volume {
     ) -> Result<UploadResponse, VolumeErrors> {
         let qs_string = serde_qs::to_string(options)?;
 
-        let client = reqwest::Client::builder().build()?;
-
-        let req = client
+        let mut builder = self
+            .client
+            .http()
             .post(concat_string!(
                 self.to_string(),
                 "/",
@@ -159,28 +517,39 @@ impl Volume {
                 "?",
                 qs_string
             ))
-            .multipart(data)
-            .send()
-            .await?;
+            .multipart(data);
+
+        if let Some(options) = options {
+            builder = options.apply_headers(builder, false);
+        }
+
+        let req = builder.send().await?;
 
         match req.status() {
             reqwest::StatusCode::CREATED => Ok(req.json::<UploadResponse>().await?),
-            _ => Err(VolumeErrors::NotCreated(req.text().await?)),
+            _ => Err(VolumeErrors::from_response("upload", req).await),
         }
     }
 
-    /// Uploads a file in bytes
-    pub async fn upload_file_bytes(
+    /// Uploads the content of a reader without buffering it in memory
+    ///
+    /// With a known length the Content-Length header is set, otherwise the body is sent chunked.
+    /// The reader is consumed by the request so this is never retried.
+    pub async fn upload_stream<R>(
         &self,
         fid: &FID,
-        data: &Bytes,
+        reader: R,
+        len: Option<u64>,
         options: &Option<UploadFileOptions>,
-    ) -> Result<UploadResponse, VolumeErrors> {
+    ) -> Result<UploadResponse, VolumeErrors>
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
         let qs_string = serde_qs::to_string(options)?;
 
-        let client = reqwest::Client::builder().build()?;
-
-        let req = client
+        let mut builder = self
+            .client
+            .http()
             .put(concat_string!(
                 self.to_string(),
                 "/",
@@ -188,15 +557,93 @@ impl Volume {
                 "?",
                 qs_string
             ))
-            .body(data.clone())
-            .send()
-            .await?;
+            .body(Body::wrap_stream(ReaderStream::new(reader)));
+
+        if let Some(len) = len {
+            builder = builder.header(header::CONTENT_LENGTH, len);
+        }
+
+        if let Some(options) = options {
+            builder = options.apply_headers(builder, true);
+        }
+
+        let req = builder.send().await?;
 
         match req.status() {
             reqwest::StatusCode::CREATED => Ok(req.json::<UploadResponse>().await?),
-            _ => Err(VolumeErrors::NotCreated(req.text().await?)),
+            _ => Err(VolumeErrors::from_response("upload", req).await),
         }
     }
+
+    /// Streams a file from disk as multipart/form-data
+    ///
+    /// The file name and the mime type are inferred from the path
+    /// unless they are set in the options
+    pub async fn upload_path(
+        &self,
+        fid: &FID,
+        path: &Path,
+        options: &Option<UploadFileOptions>,
+    ) -> Result<UploadResponse, VolumeErrors> {
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+
+        let content_type = match options.as_ref().and_then(|o| o.content_type.clone()) {
+            Some(content_type) => content_type,
+            None => mime_guess::from_path(path).first_or_octet_stream().to_string(),
+        };
+        let file_name = match options.as_ref().and_then(|o| o.file_name.clone()) {
+            Some(file_name) => Some(file_name),
+            None => path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        };
+
+        let mut part = Part::stream_with_length(Body::wrap_stream(ReaderStream::new(file)), len)
+            .mime_str(&content_type)?;
+
+        if let Some(name) = file_name {
+            part = part.file_name(name);
+        }
+
+        self.upload_file_form(fid, Form::new().part("file", part), options)
+            .await
+    }
+
+    /// Uploads a file in bytes
+    ///
+    /// Writing the same bytes to an assigned file id is idempotent so this is
+    /// retried according to the [retry policy](crate::client::WeedClient::retry) of the client
+    pub async fn upload_file_bytes(
+        &self,
+        fid: &FID,
+        data: &Bytes,
+        options: &Option<UploadFileOptions>,
+    ) -> Result<UploadResponse, VolumeErrors> {
+        let url = concat_string!(
+            self.to_string(),
+            "/",
+            fid.to_string(),
+            "?",
+            serde_qs::to_string(options)?
+        );
+
+        self.client
+            .retry()
+            .run(|| async {
+                let mut builder = self.client.http().put(&url).body(data.clone());
+
+                if let Some(options) = options {
+                    builder = options.apply_headers(builder, true);
+                }
+
+                let req = builder.send().await?;
+
+                match req.status() {
+                    reqwest::StatusCode::CREATED => Ok(req.json::<UploadResponse>().await?),
+                    _ => Err(VolumeErrors::from_response("upload", req).await),
+                }
+            })
+            .await
+    }
 }
 
 #[derive(Debug)]
@@ -229,6 +676,110 @@ pub struct GetFileOptions {
     pub crop_x2: Option<u32>,
     pub crop_y1: Option<u32>,
     pub crop_y2: Option<u32>,
+    /// false returns the raw chunk manifest instead of the joined chunks
+    pub cm: Option<bool>,
+    /// Read JWT, only needed if the cluster has jwt.signing.read.key set
+    #[serde(skip)]
+    pub jwt: Option<String>,
+}
+
+/// Adds the read JWT of the options as bearer token
+fn bearer(builder: RequestBuilder, options: &Option<GetFileOptions>) -> RequestBuilder {
+    match options.as_ref().and_then(|o| o.jwt.as_ref()) {
+        Some(jwt) => builder.bearer_auth(jwt),
+        None => builder,
+    }
+}
+
+/// Quotes a bare ETag like the one of an [UploadResponse], quoted and weak ones are kept
+fn quote_e_tag(e_tag: &str) -> String {
+    match e_tag.starts_with('"') || e_tag.starts_with("W/") || e_tag == "*" {
+        true => e_tag.to_string(),
+        false => concat_string!("\"", e_tag, "\""),
+    }
+}
+
+/// Options for the volume function [get_file_partial](Volume::get_file_partial)
+#[derive(Debug, Default, Clone)]
+pub struct PartialReadOptions {
+    /// Only reads part of the file, answered with 206 Partial Content
+    pub range: Option<ByteRange>,
+    /// Answered with 304 Not Modified if the ETag still matches, bare ETags get quoted
+    pub if_none_match: Option<String>,
+    /// Answered with 304 Not Modified if the file did not change since
+    pub if_modified_since: Option<SystemTime>,
+}
+
+/// Return type of the volume function [get_file_partial](Volume::get_file_partial)
+#[derive(Debug)]
+pub enum FileRead {
+    /// 200 OK with the whole file
+    Full {
+        data: Bytes,
+        e_tag: Option<String>,
+        pairs: HashMap<String, String>,
+    },
+    /// 206 Partial Content with the requested range
+    Partial {
+        data: Bytes,
+        range: ContentRange,
+        e_tag: Option<String>,
+        pairs: HashMap<String, String>,
+    },
+    /// 304 Not Modified, the cached copy is still valid
+    NotModified,
+}
+
+/// Metadata of a file stored on a volume server, see [head_file](Volume::head_file)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileMeta {
+    pub size: Option<u64>,
+    /// ETag as sent by the server including the quotes, usable as [if_none_match](PartialReadOptions::if_none_match)
+    pub e_tag: Option<String>,
+    pub content_type: Option<String>,
+    pub last_modified: Option<SystemTime>,
+    /// File name from the Content-Disposition header
+    pub file_name: Option<String>,
+    /// Custom pairs sent as Seaweed-<Name> headers, keyed by name without the prefix
+    pub pairs: HashMap<String, String>,
+}
+
+impl FileMeta {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> FileMeta {
+        let get = |name: header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        let pairs = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_str().strip_prefix("seaweed-")?;
+                Some((key.to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+
+        FileMeta {
+            size: get(header::CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+            e_tag: get(header::ETAG),
+            content_type: get(header::CONTENT_TYPE),
+            last_modified: get(header::LAST_MODIFIED)
+                .and_then(|v| httpdate::parse_http_date(&v).ok()),
+            file_name: get(header::CONTENT_DISPOSITION).and_then(|v| parse_file_name(&v)),
+            pairs,
+        }
+    }
+}
+
+/// Takes the file name out of a header like inline; filename="hello.txt"
+fn parse_file_name(content_disposition: &str) -> Option<String> {
+    content_disposition
+        .split(';')
+        .filter_map(|part| part.trim().strip_prefix("filename="))
+        .map(|name| name.trim_matches('"').to_string())
+        .next()
 }
 
 fn serialize_replicated<S>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
@@ -245,7 +796,7 @@ where
 }
 
 /// Options for the volume function [upload_file_bytes](Volume::upload_file_bytes)
-#[derive(Serialize, Debug, Default)]
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct UploadFileOptions {
     #[serde(rename = "type")]
     #[serde(serialize_with = "serialize_replicated")]
@@ -254,6 +805,48 @@ pub struct UploadFileOptions {
     pub ts: Option<u64>,
     /// content is a chunk manifest file
     pub cm: Option<bool>,
+    /// JWT returned by [assign_key](crate::master::Master::assign_key), sent as bearer token
+    #[serde(skip)]
+    pub jwt: Option<String>,
+    /// Content-Type stored with the file, only used for raw bodies and [upload_path](Volume::upload_path)
+    #[serde(skip)]
+    pub content_type: Option<String>,
+    /// File name stored with the file, only used for raw bodies and [upload_path](Volume::upload_path)
+    #[serde(skip)]
+    pub file_name: Option<String>,
+    /// Custom pairs stored with the file, sent as Seaweed-<Name> headers
+    ///
+    /// Header names are case insensitive, reads return the names in lower case
+    #[serde(skip)]
+    pub pairs: HashMap<String, String>,
+}
+
+impl UploadFileOptions {
+    /// Adds the headers that are not part of the query string,
+    /// multipart forms carry their own content type and file name
+    fn apply_headers(&self, mut builder: RequestBuilder, raw_body: bool) -> RequestBuilder {
+        if let Some(jwt) = &self.jwt {
+            builder = builder.bearer_auth(jwt);
+        }
+
+        for (name, value) in &self.pairs {
+            builder = builder.header(concat_string!("Seaweed-", name), value);
+        }
+
+        if raw_body {
+            if let Some(content_type) = &self.content_type {
+                builder = builder.header(header::CONTENT_TYPE, content_type);
+            }
+            if let Some(file_name) = &self.file_name {
+                builder = builder.header(
+                    header::CONTENT_DISPOSITION,
+                    concat_string!("inline; filename=\"", file_name, "\""),
+                );
+            }
+        }
+
+        builder
+    }
 }
 
 /// Return type for the volume function [upload_file_bytes](Volume::upload_file_bytes)
@@ -264,6 +857,13 @@ pub struct UploadResponse {
     pub e_tag: String,
 }
 
+/// Options for the volume function [delete_file](Volume::delete_file)
+#[derive(Debug, Default, Clone)]
+pub struct DeleteFileOptions {
+    /// Write JWT, only needed if the cluster has jwt.signing.key set
+    pub jwt: Option<String>,
+}
+
 /// Return type for the volume function [delete_file](Volume::delete_file)
 #[derive(Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
@@ -271,22 +871,222 @@ pub struct DeleteResponse {
     pub size: usize,
 }
 
+/// Result of a single file id of [delete_many](Volume::delete_many)
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DeleteResult {
+    pub fid: FID,
+    /// 202 if the file was deleted, 404 if it did not exist.
+    /// None if the volume server could not be asked
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Size of the deleted file
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default, deserialize_with = "deserialize_error")]
+    pub error: Option<String>,
+}
+
+impl DeleteResult {
+    /// The file was deleted by this request
+    pub fn is_deleted(&self) -> bool {
+        self.status == Some(202)
+    }
+
+    /// The file is gone, either deleted now or it did not exist before
+    pub fn is_gone(&self) -> bool {
+        self.is_deleted() || self.status == Some(404)
+    }
+}
+
+/// SeaweedFS omits the error or sends an empty string on success
+fn deserialize_error<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let error = Option::<String>::deserialize(deserializer)?;
+    Ok(error.filter(|e| !e.is_empty()))
+}
+
+/// Return type for the volume function [status](Volume::status)
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct VolumeServerStatus {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub volumes: Option<Vec<VolumeInfo>>,
+    #[serde(default)]
+    pub ec_volumes: Option<Vec<EcVolume>>,
+    #[serde(default)]
+    pub disk_statuses: Option<Vec<DiskStatus>>,
+}
+
+impl VolumeServerStatus {
+    /// Whether the volume server holds the volume, plain or erasure coded
+    pub fn holds(&self, volume_id: u32) -> bool {
+        let plain = self.volumes.iter().flatten().any(|v| v.id == volume_id);
+        let ec = self.ec_volumes.iter().flatten().any(|v| v.volume_id == volume_id);
+
+        plain || ec
+    }
+}
+
+/// Return type for the volume function [disk_stats](Volume::disk_stats)
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiskStatsResponse {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub disk_statuses: Option<Vec<DiskStatus>>,
+}
+
+/// An erasure coded volume with the shards stored on this volume server
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct EcVolume {
+    pub volume_id: u32,
+    #[serde(default)]
+    pub collection: String,
+    #[serde(default)]
+    pub shards: Option<Vec<EcShard>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct EcShard {
+    pub shard_id: u8,
+}
+
+/// Usage of a directory holding volumes, sizes in bytes
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct DiskStatus {
+    #[serde(default)]
+    pub dir: String,
+    #[serde(default)]
+    pub all: u64,
+    #[serde(default)]
+    pub used: u64,
+    #[serde(default)]
+    pub free: u64,
+    #[serde(default)]
+    pub percent_free: f32,
+    #[serde(default)]
+    pub percent_used: f32,
+    #[serde(default)]
+    pub disk_type: String,
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, SystemTime};
+
     use bytes::Bytes;
     use reqwest::multipart::{Part, Form};
 
-    use crate::master::{AssignKeyOptions, Master};
+    use crate::master::AssignKeyOptions;
+    use crate::testing::MockServer;
 
     use crate::utils::FID;
     use crate::volume::Volume;
 
-    use super::{UploadFileOptions, VolumeErrors};
+    use super::{
+        DeleteResult, FileMeta, FileRead, PartialReadOptions, UploadFileOptions, VolumeErrors,
+        VolumeServerStatus,
+    };
+
+    #[test]
+    fn parse_resp_status() {
+        let data = r#"{
+            "DiskStatuses": [{
+                "dir": "/data",
+                "all": 1000,
+                "used": 400,
+                "free": 600,
+                "percent_free": 60,
+                "percent_used": 40,
+                "disk_type": "hdd"
+            }],
+            "EcVolumes": [{"VolumeId": 7, "Collection": "", "Shards": [{"ShardId": 0}, {"ShardId": 3}]}],
+            "Version": "30GB 3.45",
+            "Volumes": [{"Id": 1, "Size": 1024, "FileCount": 3, "ReadOnly": false, "Collection": "pics"}]
+        }"#;
+
+        let status = serde_json::from_str::<VolumeServerStatus>(data).unwrap();
+        assert_eq!("pics", status.volumes.unwrap()[0].collection);
+        assert_eq!(2, status.ec_volumes.unwrap()[0].shards.as_ref().unwrap().len());
+        assert_eq!(600, status.disk_statuses.unwrap()[0].free);
+    }
+
+    #[test]
+    fn parse_delete_results() {
+        let data = r#"[
+            {"fid": "3,01637037d6", "size": 12, "status": 202},
+            {"fid": "3,01637037d7", "size": 0, "status": 404, "error": ""},
+            {"fid": "3,01637037d8", "size": 0, "status": 406, "error": "cookie mismatch"}
+        ]"#;
+        let results = serde_json::from_str::<Vec<DeleteResult>>(data).unwrap();
+
+        assert!(results[0].is_deleted());
+        assert_eq!(12, results[0].size);
+        assert!(results[1].is_gone() && !results[1].is_deleted());
+        assert_eq!(None, results[1].error);
+        assert!(!results[2].is_gone());
+        assert_eq!(Some("cookie mismatch".to_string()), results[2].error);
+    }
+
+    #[test]
+    fn parse_file_meta() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("Content-Length", "12".parse().unwrap());
+        headers.insert("Etag", "\"ed076287\"".parse().unwrap());
+        headers.insert("Content-Type", "text/plain".parse().unwrap());
+        headers.insert("Last-Modified", "Mon, 20 Mar 2023 10:00:00 GMT".parse().unwrap());
+        headers.insert("Content-Disposition", "inline; filename=\"hello.txt\"".parse().unwrap());
+        headers.insert("Seaweed-Owner", "alice".parse().unwrap());
+
+        let meta = FileMeta::from_headers(&headers);
+
+        assert_eq!(Some(12), meta.size);
+        assert_eq!(Some("\"ed076287\"".to_string()), meta.e_tag);
+        assert_eq!(Some("text/plain".to_string()), meta.content_type);
+        assert!(meta.last_modified.is_some());
+        assert_eq!(Some("hello.txt".to_string()), meta.file_name);
+        assert_eq!(Some(&"alice".to_string()), meta.pairs.get("owner"));
+    }
+
+    #[test]
+    fn upload_headers() {
+        let options = UploadFileOptions {
+            jwt: Some("token".to_string()),
+            content_type: Some("text/plain".to_string()),
+            file_name: Some("hello.txt".to_string()),
+            pairs: [("Owner".to_string(), "alice".to_string())].into(),
+            ..Default::default()
+        };
+
+        let client = reqwest::Client::new();
+        let raw = options
+            .apply_headers(client.put("http://localhost:8080/3,01637037d6"), true)
+            .build()
+            .unwrap();
+
+        assert_eq!("Bearer token", raw.headers()["authorization"]);
+        assert_eq!("alice", raw.headers()["seaweed-owner"]);
+        assert_eq!("text/plain", raw.headers()["content-type"]);
+        assert_eq!("inline; filename=\"hello.txt\"", raw.headers()["content-disposition"]);
 
-    static MASTER_HOST: &str = "localhost";
-    static MASTER_PORT: u16 = 8333;
+        let form = options
+            .apply_headers(client.post("http://localhost:8080/3,01637037d6"), false)
+            .build()
+            .unwrap();
+
+        assert_eq!("alice", form.headers()["seaweed-owner"]);
+        assert!(form.headers().get("content-type").is_none());
+    }
 
     #[test]
+    #[allow(clippy::single_match)]
     fn serialize_replicated() {
         let data = UploadFileOptions {
             replicated: Some(true),
@@ -302,10 +1102,8 @@ mod tests {
 
     #[tokio::test]
     async fn upload_download_delete() {
-        let master = Master {
-            host: MASTER_HOST.to_string(),
-            port: Some(MASTER_PORT),
-        };
+        let server = MockServer::start().await;
+        let master = server.master();
 
         let options: AssignKeyOptions = Default::default();
         let master_resp = master.assign_key(&Some(options)).await;
@@ -365,21 +1163,57 @@ mod tests {
         let down_resp = volume.get_file_bytes(&fid, &None).await;
 
         match down_resp {
-            Err(x) => match x {
-                VolumeErrors::FileNotFound => println!("file deleted"),
-                _ => panic!("file not deleted"),
-            },
+            Err(VolumeErrors::FileNotFound(_)) => println!("file deleted"),
             _ => panic!("file not deleted"),
         }
     }
 
     #[tokio::test]
-    async fn upload_multipart() {
-        let master = Master {
-            host: MASTER_HOST.to_string(),
-            port: Some(MASTER_PORT),
+    async fn upload_stream_download_stream() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
+        let options: AssignKeyOptions = Default::default();
+        let master_resp = master.assign_key(&Some(options)).await;
+
+        let (fid, volume) = match master_resp {
+            Ok(x) => (x.fid, Volume::from_str(&x.location.url).unwrap()),
+            _ => panic!("failed to assign key"),
         };
 
+        let data: &'static [u8] = b"Hello World!";
+        let file_resp = volume
+            .upload_stream(&fid, data, Some(data.len() as u64), &None)
+            .await;
+
+        match file_resp {
+            Ok(x) => assert_eq!(data.len(), x.size),
+            Err(err) => {
+                println!("{}", err);
+                panic!("failed to upload stream");
+            }
+        }
+
+        let mut reader = match volume.get_file_stream(&fid, &None).await {
+            Ok(stream) => stream.into_reader(),
+            Err(err) => {
+                println!("{}", err);
+                panic!("failed to download stream");
+            }
+        };
+
+        let mut body = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut body)
+            .await
+            .unwrap();
+        assert_eq!(data, body.as_slice());
+    }
+
+    #[tokio::test]
+    async fn upload_multipart() {
+        let server = MockServer::start().await;
+        let master = server.master();
+
         let options: AssignKeyOptions = Default::default();
         let master_resp = master.assign_key(&Some(options)).await;
 
@@ -422,4 +1256,84 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn conditional_reads() {
+        let server = MockServer::start().await;
+        let assigned = server.master().assign_key(&None).await.unwrap();
+        let volume = server.volume();
+        let fid = assigned.fid;
+
+        let uploaded = volume
+            .upload_file_bytes(&fid, &Bytes::from("Hello World!"), &None)
+            .await
+            .unwrap();
+
+        let read = PartialReadOptions::default();
+        let e_tag = match volume.get_file_partial(&fid, &read, &None).await.unwrap() {
+            FileRead::Full { e_tag, .. } => e_tag,
+            read => panic!("expected the whole file, got {:?}", read),
+        };
+
+        // the ETag of a read and the bare one of the upload are both valid validators
+        for e_tag in [e_tag, Some(uploaded.e_tag)] {
+            let read = PartialReadOptions {
+                if_none_match: e_tag,
+                ..Default::default()
+            };
+            let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+            assert!(matches!(resp, FileRead::NotModified));
+        }
+
+        let read = PartialReadOptions {
+            if_none_match: Some("\"00000000\"".to_string()),
+            ..Default::default()
+        };
+        let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+        assert!(matches!(resp, FileRead::Full { .. }));
+
+        let read = PartialReadOptions {
+            if_modified_since: Some(SystemTime::now() + Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+        assert!(matches!(resp, FileRead::NotModified));
+
+        let read = PartialReadOptions {
+            if_modified_since: Some(SystemTime::now() - Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+        assert!(matches!(resp, FileRead::Full { .. }));
+    }
+
+    #[tokio::test]
+    async fn head_file_validators() {
+        let server = MockServer::start().await;
+        let assigned = server.master().assign_key(&None).await.unwrap();
+        let volume = server.volume();
+        let fid = assigned.fid;
+
+        volume
+            .upload_file_bytes(&fid, &Bytes::from("Hello World!"), &None)
+            .await
+            .unwrap();
+        let meta = volume.head_file(&fid, &None).await.unwrap();
+        assert!(meta.e_tag.is_some());
+        assert!(meta.last_modified.is_some());
+
+        let read = PartialReadOptions {
+            if_none_match: meta.e_tag,
+            ..Default::default()
+        };
+        let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+        assert!(matches!(resp, FileRead::NotModified));
+
+        let read = PartialReadOptions {
+            if_modified_since: meta.last_modified,
+            ..Default::default()
+        };
+        let resp = volume.get_file_partial(&fid, &read, &None).await.unwrap();
+        assert!(matches!(resp, FileRead::NotModified));
+    }
 }